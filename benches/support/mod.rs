@@ -0,0 +1,122 @@
+//! Helpers shared by the benchmarks and the library tests, which include this file as a module.
+
+#[cfg(feature = "polars")]
+use std::collections::HashMap;
+
+#[cfg(feature = "polars")]
+use geo_data_tiler::{CellId, SpatialScheme, Tiler};
+#[cfg(feature = "polars")]
+use polars::prelude::*;
+
+/// Uniform values in [0, 1) from a fixed linear congruential sequence starting at `seed`.
+pub fn lcg(seed: u64) -> impl FnMut() -> f64 {
+    let mut state = seed;
+    move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The node count and weight of each tile of `tiler`, computed like `Tiler::get_cell_tiles` was
+/// before single-pass tiling: by rebuilding, grouping and joining a DataFrame per precision.
+#[cfg(feature = "polars")]
+pub fn cell_counts_per_level<S: SpatialScheme>(
+    tiler: &Tiler<S>,
+) -> Result<HashMap<CellId, (i64, f64)>, PolarsError> {
+    let node_count: Vec<i64> = tiler.binary_hash_count.values().copied().collect();
+    let binary_hash: Vec<u64> = tiler.binary_hash_count.keys().map(|cell| cell.0).collect();
+    let weight: Vec<f64> = tiler
+        .binary_hash_count
+        .keys()
+        .map(|binary_hash_value| tiler.binary_hash_weight[binary_hash_value])
+        .collect();
+
+    let mut binary_hash_count_df = df!(
+        "node_count" => node_count,
+        "weight" => weight,
+        "binary_hash" => binary_hash
+    )?;
+
+    let mut cell_counts = HashMap::new();
+
+    for precision in tiler.spatial_scheme.min_precision()..=tiler.binary_hash_precision {
+        let sliced_binary_hash: Vec<u64> = binary_hash_count_df
+            .column("binary_hash")?
+            .u64()?
+            .into_no_null_iter()
+            .map(|binary_hash_value| {
+                tiler
+                    .spatial_scheme
+                    .parent(CellId(binary_hash_value), precision)
+                    .0
+            })
+            .collect();
+        let temp_binary_hash_count_df = binary_hash_count_df
+            .with_column(Series::new("sliced_binary_hash", sliced_binary_hash))?
+            .clone();
+
+        let grouped_binary_hash_df = temp_binary_hash_count_df
+            .lazy()
+            .group_by([col("sliced_binary_hash")])
+            .agg([
+                col("node_count").sum().alias("total_node_count"),
+                col("weight").sum().alias("total_weight"),
+                col("binary_hash").reverse().alias("binary_hashes"),
+            ])
+            .collect()?;
+        let max_weight = lit(tiler.max_allowed_features_in_binary_hash as f64);
+        let binary_hashes_over_max_allowed_features_df = grouped_binary_hash_df
+            .clone()
+            .lazy()
+            .filter(col("total_weight").gt(max_weight.clone()))
+            .collect()?
+            .explode(["binary_hashes"])?
+            .rename("binary_hashes", "binary_hash")?
+            .drop_many(&["sliced_binary_hash", "total_node_count", "total_weight"])
+            .left_join(&binary_hash_count_df, ["binary_hash"], ["binary_hash"])?;
+        binary_hash_count_df = binary_hashes_over_max_allowed_features_df;
+
+        let binary_hashes_under_max_allowed_features_df = grouped_binary_hash_df
+            .lazy()
+            .filter(col("total_weight").lt_eq(max_weight))
+            .collect()?;
+        insert_cell_counts(
+            &mut cell_counts,
+            &binary_hashes_under_max_allowed_features_df,
+            ["sliced_binary_hash", "total_node_count", "total_weight"],
+        )?;
+    }
+
+    insert_cell_counts(
+        &mut cell_counts,
+        &binary_hash_count_df,
+        ["binary_hash", "node_count", "weight"],
+    )?;
+
+    Ok(cell_counts)
+}
+
+/// Adds the node count and weight of each row of `binary_hash_df`, reading the cell, node count
+/// and weight from the given columns.
+#[cfg(feature = "polars")]
+fn insert_cell_counts(
+    cell_counts: &mut HashMap<CellId, (i64, f64)>,
+    binary_hash_df: &DataFrame,
+    [binary_hash_column, node_count_column, weight_column]: [&str; 3],
+) -> Result<(), PolarsError> {
+    let binary_hash_list = binary_hash_df.column(binary_hash_column)?.u64()?;
+    let node_count_list = binary_hash_df.column(node_count_column)?.i64()?;
+    let weight_list = binary_hash_df.column(weight_column)?.f64()?;
+
+    for ((node_count, weight), binary_hash) in node_count_list
+        .into_no_null_iter()
+        .zip(weight_list.into_no_null_iter())
+        .zip(binary_hash_list.into_no_null_iter())
+    {
+        cell_counts.insert(CellId(binary_hash), (node_count, weight));
+    }
+
+    Ok(())
+}
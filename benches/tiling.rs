@@ -0,0 +1,43 @@
+mod support;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use geo_data_tiler::Tiler;
+
+/// Coordinates from a fixed linear congruential sequence, a third of them clustered in one city.
+fn coordinates(count: usize) -> Vec<(f64, f64)> {
+    let mut next = support::lcg(42);
+    (0..count)
+        .map(|index| {
+            if index % 3 == 0 {
+                (48.2 + next() * 0.1, 16.3 + next() * 0.1)
+            } else {
+                (next() * 160.0 - 80.0, next() * 360.0 - 180.0)
+            }
+        })
+        .collect()
+}
+
+fn tiling(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("tiling");
+    for count in [10_000, 100_000] {
+        let mut tiler = Tiler::new(24, 100).unwrap();
+        for (latitude, longitude) in coordinates(count) {
+            tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("single_pass", count),
+            &tiler,
+            |bencher, tiler| bencher.iter(|| tiler.get_cell_tiles().unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("per_level", count),
+            &tiler,
+            |bencher, tiler| bencher.iter(|| support::cell_counts_per_level(tiler).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tiling);
+criterion_main!(benches);
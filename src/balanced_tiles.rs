@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::binary_hash_tile::BinaryHashTile;
+
+/// Tiles from `Tiler::get_tiles_with_count`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalancedTiles {
+    pub binary_hash_tiles: HashMap<String, BinaryHashTile>,
+    /// Weight of the heaviest tile divided by the weight of the lightest tile. Infinite if only
+    /// the lightest tile has no weight, and 1 if no tile has any weight.
+    pub imbalance_ratio: f64,
+}
+
+impl BalancedTiles {
+    pub(crate) fn new(binary_hash_tiles: HashMap<String, BinaryHashTile>) -> Self {
+        let weights = binary_hash_tiles
+            .values()
+            .map(|binary_hash_tile| binary_hash_tile.weight);
+        let max_weight = weights.clone().fold(0.0, f64::max);
+        let min_weight = weights.fold(f64::INFINITY, f64::min);
+        let imbalance_ratio = if max_weight == 0.0 {
+            1.0
+        } else {
+            max_weight / min_weight
+        };
+        BalancedTiles {
+            binary_hash_tiles,
+            imbalance_ratio,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use geohashrust::BoundingBox;
+
+use crate::cell_id::{CellId, MAX_LEVEL};
+use crate::crs::Crs;
+use crate::spatial_scheme::SpatialScheme;
+
+/// The `geohashrust` binary hash: alternating longitude and latitude bisection of a root extent,
+/// the whole globe by default.
+///
+/// Each bisection is one `CellId` level, so precisions go up to 63. Binary hashes are relative to
+/// the root extent: the same binary hash is a different area under a different root extent. The
+/// root extent is in `crs`, which may be a projected CRS to bisect x and y in metres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinaryHashScheme {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+    pub crs: Crs,
+}
+
+impl Default for BinaryHashScheme {
+    fn default() -> Self {
+        BinaryHashScheme {
+            min_lon: -180.0,
+            min_lat: -90.0,
+            max_lon: 180.0,
+            max_lat: 90.0,
+            crs: Crs::Wgs84,
+        }
+    }
+}
+
+impl BinaryHashScheme {
+    /// Bisects `root_extent` instead of the whole globe, such as the extent of a country or city.
+    pub fn with_root_extent(root_extent: BoundingBox) -> Self {
+        BinaryHashScheme {
+            min_lon: root_extent.min_lon,
+            min_lat: root_extent.min_lat,
+            max_lon: root_extent.max_lon,
+            max_lat: root_extent.max_lat,
+            crs: Crs::Wgs84,
+        }
+    }
+
+    /// Bisects x and y of `crs` within its extent instead of longitude and latitude.
+    pub fn projected(crs: Crs) -> Self {
+        BinaryHashScheme {
+            crs,
+            ..BinaryHashScheme::with_root_extent(crs.extent())
+        }
+    }
+}
+
+impl SpatialScheme for BinaryHashScheme {
+    fn max_precision(&self) -> u8 {
+        MAX_LEVEL
+    }
+
+    fn crs(&self) -> Crs {
+        self.crs
+    }
+
+    fn extent(&self) -> BoundingBox {
+        BoundingBox::from_coordinates(self.min_lat, self.max_lat, self.min_lon, self.max_lon)
+    }
+
+    fn encode(&self, latitude: f64, longitude: f64, precision: u8) -> CellId {
+        let mut bounds = self.extent();
+        let mut bits = 0u64;
+        for bit_index in 0..precision {
+            let is_upper_half = if bit_index % 2 == 0 {
+                let middle = (bounds.min_lon + bounds.max_lon) / 2.0;
+                let is_upper_half = longitude > middle;
+                if is_upper_half {
+                    bounds.min_lon = middle;
+                } else {
+                    bounds.max_lon = middle;
+                }
+                is_upper_half
+            } else {
+                let middle = (bounds.min_lat + bounds.max_lat) / 2.0;
+                let is_upper_half = latitude > middle;
+                if is_upper_half {
+                    bounds.min_lat = middle;
+                } else {
+                    bounds.max_lat = middle;
+                }
+                is_upper_half
+            };
+            bits = (bits << 1) | u64::from(is_upper_half);
+        }
+        CellId::from_bits(bits, precision)
+    }
+
+    fn precision(&self, cell: CellId) -> u8 {
+        cell.level()
+    }
+
+    fn parent(&self, cell: CellId, precision: u8) -> CellId {
+        cell.parent(precision)
+    }
+
+    fn children(&self, cell: CellId) -> Vec<CellId> {
+        cell.children().to_vec()
+    }
+
+    fn root_cells(&self) -> Vec<CellId> {
+        self.children(CellId::ROOT)
+    }
+
+    fn bounds(&self, cell: CellId) -> BoundingBox {
+        let mut bounds = self.extent();
+        let level = cell.level();
+        let bits = cell.bits();
+        for bit_index in 0..level {
+            let is_upper_half = (bits >> (level - 1 - bit_index)) & 1 == 1;
+            if bit_index % 2 == 0 {
+                let middle = (bounds.min_lon + bounds.max_lon) / 2.0;
+                if is_upper_half {
+                    bounds.min_lon = middle;
+                } else {
+                    bounds.max_lon = middle;
+                }
+            } else {
+                let middle = (bounds.min_lat + bounds.max_lat) / 2.0;
+                if is_upper_half {
+                    bounds.min_lat = middle;
+                } else {
+                    bounds.max_lat = middle;
+                }
+            }
+        }
+        bounds
+    }
+
+    fn token(&self, cell: CellId) -> String {
+        let level = cell.level();
+        let bits = cell.bits();
+        (0..level)
+            .map(|bit_index| {
+                if (bits >> (level - 1 - bit_index)) & 1 == 1 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect()
+    }
+
+    fn parse_token(&self, token: &str) -> CellId {
+        let bits = token.chars().fold(0u64, |bits, character| match character {
+            '0' => bits << 1,
+            '1' => (bits << 1) | 1,
+            _ => panic!("invalid binary hash {token}"),
+        });
+        CellId::from_bits(bits, token.len() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geohashrust::{BinaryHash, GeoLocation};
+
+    use super::*;
+    use crate::tiler::Tiler;
+    use crate::tiler_error::TilerError;
+
+    #[test]
+    fn cells_nest_within_their_parents() {
+        let binary_hash_scheme = BinaryHashScheme::default();
+        let cell = binary_hash_scheme.encode(48.2, 16.4, 12);
+        let parent = binary_hash_scheme.parent(cell, 7);
+        let bounds = binary_hash_scheme.bounds(cell);
+        let parent_bounds = binary_hash_scheme.bounds(parent);
+
+        assert_eq!(binary_hash_scheme.precision(parent), 7);
+        assert!(parent.contains(cell));
+        assert!(binary_hash_scheme
+            .token(cell)
+            .starts_with(&binary_hash_scheme.token(parent)));
+        assert!(binary_hash_scheme
+            .children(binary_hash_scheme.parent(cell, 11))
+            .contains(&cell));
+        assert!(parent_bounds.min_lon <= bounds.min_lon && bounds.max_lon <= parent_bounds.max_lon);
+        assert!(parent_bounds.min_lat <= bounds.min_lat && bounds.max_lat <= parent_bounds.max_lat);
+    }
+
+    #[test]
+    fn matches_geohashrust_binary_hash() {
+        let binary_hash_scheme = BinaryHashScheme::default();
+        for (latitude, longitude) in [(48.2, 16.4), (-33.87, 151.21), (0.0, 0.0), (90.0, -180.0)] {
+            let binary_hash = BinaryHash::encode(
+                &GeoLocation {
+                    latitude,
+                    longitude,
+                },
+                40,
+            );
+            let cell = binary_hash_scheme.encode(latitude, longitude, 40);
+
+            assert_eq!(binary_hash_scheme.token(cell), binary_hash.to_string());
+            assert_eq!(
+                binary_hash_scheme.parse_token(&binary_hash.to_string()),
+                cell
+            );
+            assert!(binary_hash_scheme.bounds(cell) == binary_hash.decode());
+        }
+    }
+
+    #[test]
+    fn with_scheme_matches_new() {
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        let mut scheme_tiler = Tiler::with_scheme(BinaryHashScheme::default(), 11, 2).unwrap();
+        for (latitude, longitude) in [(1.0, 1.0), (1.0, 2.0), (-1.0, 2.0), (1.0, -1.0)] {
+            tiler.add_coordinate(latitude, longitude).unwrap();
+            scheme_tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+
+        assert_eq!(
+            scheme_tiler.get_tiles().unwrap(),
+            tiler.get_tiles().unwrap()
+        );
+    }
+
+    #[test]
+    fn bisects_a_custom_root_extent() {
+        let root_extent = BoundingBox::from_coordinates(48.1, 48.3, 16.2, 16.6);
+        let binary_hash_scheme = BinaryHashScheme::with_root_extent(root_extent);
+        let cell = binary_hash_scheme.encode(48.21, 16.37, 12);
+        let bounds = binary_hash_scheme.bounds(cell);
+        assert_eq!(binary_hash_scheme.token(cell), "011010001111");
+        assert!((bounds.max_lon - bounds.min_lon - 0.4 / 64.0).abs() < 1e-12);
+        assert!(bounds.min_lat <= 48.21 && 48.21 <= bounds.max_lat);
+        assert!(bounds.min_lon <= 16.37 && 16.37 <= bounds.max_lon);
+        assert!(
+            binary_hash_scheme.bounds(binary_hash_scheme.root_cells()[1]) == {
+                BoundingBox::from_coordinates(48.1, 48.3, 16.4, 16.6)
+            }
+        );
+
+        let mut tiler = Tiler::with_root_extent(root_extent, 12, 2).unwrap();
+        for (latitude, longitude) in [(48.21, 16.37), (48.2, 16.38), (48.15, 16.5)] {
+            tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+        assert!(matches!(
+            tiler.add_coordinate(47.0, 16.37),
+            Err(TilerError::InvalidCoordinate { .. })
+        ));
+        for binary_hash_tile in tiler.get_tiles().unwrap().values() {
+            assert!(16.2 <= binary_hash_tile.min_lon && binary_hash_tile.max_lon <= 16.6);
+            assert!(48.1 <= binary_hash_tile.min_lat && binary_hash_tile.max_lat <= 48.3);
+        }
+
+        let empty_extent = BoundingBox::from_coordinates(48.1, 48.1, 16.2, 16.6);
+        assert!(matches!(
+            Tiler::with_root_extent(empty_extent, 12, 2),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+    }
+}
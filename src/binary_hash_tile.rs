@@ -1,8 +1,17 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinaryHashTile {
+    /// Coordinates and geometries counted in the tile. A geometry counted in several cells of the
+    /// tile is counted once, while its `weight` is split between all of its cells.
     pub node_count: i64,
+    pub weight: f64,
     pub min_lon: f64,
     pub min_lat: f64,
     pub max_lon: f64,
     pub max_lat: f64,
+    /// Bounds in the CRS that coordinates were given in, equal to the WGS84 bounds above unless
+    /// `Tiler::input_crs` is a projected CRS.
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
 }
@@ -0,0 +1,118 @@
+/// The deepest level a `CellId` can represent.
+pub const MAX_LEVEL: u8 = 63;
+
+/// A cell of a binary hierarchy packed into a `u64`.
+///
+/// The cell's path from the root is stored in the most significant bits, followed by a single 1
+/// bit and zeros, so the level can be read back from the trailing zeros. Parents are prefixes, and
+/// all descendants of a cell fall into a contiguous range of ids around it.
+///
+/// Spatial schemes with more than two children per cell spend several levels per precision, for
+/// example two per zoom level for quadkeys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellId(pub u64);
+
+impl CellId {
+    /// The cell covering the whole hierarchy, at level 0.
+    pub const ROOT: CellId = CellId(1 << MAX_LEVEL);
+
+    /// Builds the cell whose path is the lowest `level` bits of `bits`, most significant first.
+    pub fn from_bits(bits: u64, level: u8) -> Self {
+        let path = if level == 0 {
+            0
+        } else {
+            bits << (u32::from(MAX_LEVEL - level) + 1)
+        };
+        CellId(path | CellId::lowest_bit(level))
+    }
+
+    /// The path of the cell as its lowest `level` bits.
+    pub fn bits(self) -> u64 {
+        let level = self.level();
+        if level == 0 {
+            0
+        } else {
+            self.0 >> (u32::from(MAX_LEVEL - level) + 1)
+        }
+    }
+
+    pub fn level(self) -> u8 {
+        MAX_LEVEL - self.0.trailing_zeros() as u8
+    }
+
+    fn lowest_bit(level: u8) -> u64 {
+        1 << (MAX_LEVEL - level)
+    }
+
+    /// Returns the ancestor at `level`, which must not be deeper than the cell itself.
+    pub fn parent(self, level: u8) -> Self {
+        let lowest_bit = CellId::lowest_bit(level);
+        CellId((self.0 & lowest_bit.wrapping_neg()) | lowest_bit)
+    }
+
+    /// The two children, in path order.
+    pub fn children(self) -> [CellId; 2] {
+        let child_lowest_bit = (self.0 & self.0.wrapping_neg()) >> 1;
+        [
+            CellId(self.0 - child_lowest_bit),
+            CellId(self.0 + child_lowest_bit),
+        ]
+    }
+
+    /// The descendants at `level`, in path order.
+    pub fn descendants(self, level: u8) -> impl Iterator<Item = CellId> {
+        let descendant_count = 1u64 << (level - self.level());
+        let descendant_lowest_bit = CellId::lowest_bit(level);
+        let first_descendant = self.range_min().0 - 1 + descendant_lowest_bit;
+        (0..descendant_count).map(move |k| CellId(first_descendant + 2 * k * descendant_lowest_bit))
+    }
+
+    /// Whether `other` is this cell or one of its descendants.
+    pub fn contains(self, other: CellId) -> bool {
+        self.range_min() <= other && other <= self.range_max()
+    }
+
+    /// The smallest id of any descendant.
+    pub fn range_min(self) -> CellId {
+        CellId(self.0 - ((self.0 & self.0.wrapping_neg()) - 1))
+    }
+
+    /// The largest id of any descendant.
+    pub fn range_max(self) -> CellId {
+        CellId(self.0 + ((self.0 & self.0.wrapping_neg()) - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_path_and_level() {
+        let cell_id = CellId::from_bits(0b1011, 4);
+        assert_eq!(cell_id.level(), 4);
+        assert_eq!(cell_id.bits(), 0b1011);
+        assert_eq!(cell_id.parent(2), CellId::from_bits(0b10, 2));
+        assert_eq!(cell_id.parent(0), CellId::ROOT);
+        assert_eq!(
+            cell_id.children(),
+            [CellId::from_bits(0b10110, 5), CellId::from_bits(0b10111, 5)]
+        );
+        assert_eq!(
+            CellId::from_bits(0b1, 1).descendants(3).collect::<Vec<_>>(),
+            (0b100..=0b111)
+                .map(|bits| CellId::from_bits(bits, 3))
+                .collect::<Vec<_>>()
+        );
+
+        assert!(CellId::ROOT.contains(cell_id));
+        assert!(cell_id.parent(1).contains(cell_id));
+        assert!(cell_id.contains(cell_id));
+        assert!(!cell_id.contains(cell_id.parent(3)));
+        assert!(!CellId::from_bits(0b0, 1).contains(cell_id));
+
+        let leaf_cell_id = CellId::from_bits(u64::MAX, MAX_LEVEL);
+        assert_eq!(leaf_cell_id.level(), MAX_LEVEL);
+        assert_eq!(leaf_cell_id.bits(), u64::MAX >> 1);
+    }
+}
@@ -0,0 +1,424 @@
+use std::f64::consts::FRAC_PI_4;
+
+use geohashrust::BoundingBox;
+
+use crate::tiler_error::TilerError;
+
+/// WGS84 semi-major axis in metres, also used by GRS80.
+const SEMI_MAJOR_AXIS: f64 = 6378137.0;
+const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+const GRS80_FLATTENING: f64 = 1.0 / 298.257222101;
+const UTM_SCALE_FACTOR: f64 = 0.9996;
+/// Number of points sampled along each edge of a bounding box when transforming it, so that
+/// curved edges in the target CRS are covered.
+const EDGE_SAMPLES: usize = 16;
+
+/// A coordinate reference system that coordinates can be given in and tiled in.
+///
+/// Projections are computed here rather than through PROJ. ETRS89 is treated as identical to
+/// WGS84, which is accurate to well below a metre.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Crs {
+    /// EPSG:4326, with x the longitude and y the latitude in degrees.
+    #[default]
+    Wgs84,
+    /// EPSG:3857, spherical Web Mercator in metres.
+    WebMercator,
+    /// EPSG:326xx in the northern and EPSG:327xx in the southern hemisphere.
+    Utm { zone: u8, north: bool },
+    /// EPSG:3035, ETRS89-extended / LAEA Europe.
+    LaeaEurope,
+}
+
+impl Crs {
+    pub fn from_epsg(epsg: u32) -> Result<Crs, TilerError> {
+        match epsg {
+            4326 => Ok(Crs::Wgs84),
+            3857 => Ok(Crs::WebMercator),
+            32601..=32660 => Ok(Crs::Utm {
+                zone: (epsg - 32600) as u8,
+                north: true,
+            }),
+            32701..=32760 => Ok(Crs::Utm {
+                zone: (epsg - 32700) as u8,
+                north: false,
+            }),
+            3035 => Ok(Crs::LaeaEurope),
+            _ => Err(TilerError::InvalidConfiguration(format!(
+                "unsupported CRS EPSG:{epsg}"
+            ))),
+        }
+    }
+
+    pub fn epsg(&self) -> u32 {
+        match self {
+            Crs::Wgs84 => 4326,
+            Crs::WebMercator => 3857,
+            Crs::Utm { zone, north: true } => 32600 + u32::from(*zone),
+            Crs::Utm { zone, north: false } => 32700 + u32::from(*zone),
+            Crs::LaeaEurope => 3035,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), TilerError> {
+        match self {
+            Crs::Utm { zone, .. } if !(1..=60).contains(zone) => Err(
+                TilerError::InvalidConfiguration(format!("UTM zone must be 1 to 60, got {zone}")),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The WGS84 area this CRS is meant for.
+    pub fn area_of_use(&self) -> BoundingBox {
+        match self {
+            Crs::Wgs84 => BoundingBox::from_coordinates(-90.0, 90.0, -180.0, 180.0),
+            Crs::WebMercator => {
+                BoundingBox::from_coordinates(-85.0511287798066, 85.0511287798066, -180.0, 180.0)
+            }
+            Crs::Utm { zone, north } => {
+                let min_lon = f64::from(*zone) * 6.0 - 186.0;
+                let (min_lat, max_lat) = if *north { (0.0, 84.0) } else { (-80.0, 0.0) };
+                BoundingBox::from_coordinates(min_lat, max_lat, min_lon, min_lon + 6.0)
+            }
+            Crs::LaeaEurope => BoundingBox::from_coordinates(24.6, 84.73, -35.58, 44.83),
+        }
+    }
+
+    /// The area of use in this CRS, rounded outwards to whole kilometres for projected CRSs.
+    pub fn extent(&self) -> BoundingBox {
+        if *self == Crs::Wgs84 {
+            return self.area_of_use();
+        }
+        let extent = Crs::Wgs84.transform_bounds(&self.area_of_use(), self);
+        BoundingBox::from_coordinates(
+            (extent.min_lat / 1000.0).floor() * 1000.0,
+            (extent.max_lat / 1000.0).ceil() * 1000.0,
+            (extent.min_lon / 1000.0).floor() * 1000.0,
+            (extent.max_lon / 1000.0).ceil() * 1000.0,
+        )
+    }
+
+    /// Converts `x`/`y` in this CRS to `(latitude, longitude)` in WGS84.
+    pub fn to_wgs84(&self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Crs::Wgs84 => (y, x),
+            Crs::WebMercator => (
+                (2.0 * (y / SEMI_MAJOR_AXIS).exp().atan() - 2.0 * FRAC_PI_4).to_degrees(),
+                (x / SEMI_MAJOR_AXIS).to_degrees(),
+            ),
+            Crs::Utm { zone, north } => {
+                let false_northing = if *north { 0.0 } else { 10000000.0 };
+                transverse_mercator_inverse(
+                    (x - 500000.0) / UTM_SCALE_FACTOR,
+                    (y - false_northing) / UTM_SCALE_FACTOR,
+                    utm_central_meridian(*zone),
+                )
+            }
+            Crs::LaeaEurope => LambertAzimuthalEqualArea::europe().inverse(x, y),
+        }
+    }
+
+    /// Converts WGS84 `latitude` and `longitude` to `(x, y)` in this CRS.
+    pub fn from_wgs84(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        match self {
+            Crs::Wgs84 => (longitude, latitude),
+            Crs::WebMercator => (
+                SEMI_MAJOR_AXIS * longitude.to_radians(),
+                SEMI_MAJOR_AXIS * (FRAC_PI_4 + latitude.to_radians() / 2.0).tan().ln(),
+            ),
+            Crs::Utm { zone, north } => {
+                let false_northing = if *north { 0.0 } else { 10000000.0 };
+                let (x, y) =
+                    transverse_mercator_forward(latitude, longitude, utm_central_meridian(*zone));
+                (
+                    x * UTM_SCALE_FACTOR + 500000.0,
+                    y * UTM_SCALE_FACTOR + false_northing,
+                )
+            }
+            Crs::LaeaEurope => LambertAzimuthalEqualArea::europe().forward(latitude, longitude),
+        }
+    }
+
+    /// Converts `x`/`y` in this CRS to `(x, y)` in `target_crs`.
+    pub fn transform(&self, target_crs: &Crs, x: f64, y: f64) -> (f64, f64) {
+        if self == target_crs {
+            return (x, y);
+        }
+        let (latitude, longitude) = self.to_wgs84(x, y);
+        target_crs.from_wgs84(latitude, longitude)
+    }
+
+    /// The bounding box in `target_crs` of `bounds`, whose `min_lon`/`max_lon` are x and
+    /// `min_lat`/`max_lat` are y in this CRS. Edges are sampled, so the result covers curved
+    /// edges up to the sampling density.
+    pub fn transform_bounds(&self, bounds: &BoundingBox, target_crs: &Crs) -> BoundingBox {
+        if self == target_crs {
+            return *bounds;
+        }
+
+        let mut transformed_bounds = BoundingBox {
+            min_lat: f64::INFINITY,
+            max_lat: f64::NEG_INFINITY,
+            min_lon: f64::INFINITY,
+            max_lon: f64::NEG_INFINITY,
+        };
+        for sample in 0..=EDGE_SAMPLES {
+            let fraction = sample as f64 / EDGE_SAMPLES as f64;
+            let x = bounds.min_lon + (bounds.max_lon - bounds.min_lon) * fraction;
+            let y = bounds.min_lat + (bounds.max_lat - bounds.min_lat) * fraction;
+            for (x, y) in [
+                (x, bounds.min_lat),
+                (x, bounds.max_lat),
+                (bounds.min_lon, y),
+                (bounds.max_lon, y),
+            ] {
+                let (x, y) = self.transform(target_crs, x, y);
+                transformed_bounds.min_lon = transformed_bounds.min_lon.min(x);
+                transformed_bounds.max_lon = transformed_bounds.max_lon.max(x);
+                transformed_bounds.min_lat = transformed_bounds.min_lat.min(y);
+                transformed_bounds.max_lat = transformed_bounds.max_lat.max(y);
+            }
+        }
+        transformed_bounds
+    }
+}
+
+fn utm_central_meridian(zone: u8) -> f64 {
+    f64::from(zone) * 6.0 - 183.0
+}
+
+/// Coefficients of the Krüger series for WGS84, as in Karney (2011), "Transverse Mercator with
+/// an accuracy of a few nanometers".
+struct KruegerSeries {
+    /// Eccentricity.
+    e: f64,
+    /// Radius of the rectifying sphere.
+    a: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+}
+
+impl KruegerSeries {
+    fn wgs84() -> Self {
+        let f = WGS84_FLATTENING;
+        let n = f / (2.0 - f);
+        let (n2, n3, n4, n5, n6) = (n * n, n.powi(3), n.powi(4), n.powi(5), n.powi(6));
+        KruegerSeries {
+            e: (f * (2.0 - f)).sqrt(),
+            a: SEMI_MAJOR_AXIS / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0
+                    + 7891.0 * n6 / 37800.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0
+                    - 1983433.0 * n6 / 1935360.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0
+                    + 15061.0 * n5 / 26880.0
+                    + 167603.0 * n6 / 181440.0,
+                49561.0 * n4 / 161280.0 - 179.0 * n5 / 168.0 + 6601661.0 * n6 / 7257600.0,
+                34729.0 * n5 / 80640.0 - 3418889.0 * n6 / 1995840.0,
+                212378941.0 * n6 / 319334400.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0
+                    + 96199.0 * n6 / 604800.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0
+                    - 1118711.0 * n6 / 3870720.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+                4397.0 * n4 / 161280.0 - 11.0 * n5 / 504.0 - 830251.0 * n6 / 7257600.0,
+                4583.0 * n5 / 161280.0 - 108847.0 * n6 / 3991680.0,
+                20648693.0 * n6 / 638668800.0,
+            ],
+        }
+    }
+
+    /// Converts the tangent of the geodetic latitude to the tangent of the conformal latitude.
+    fn conformal_tangent(&self, tangent: f64) -> f64 {
+        let sigma = (self.e * (self.e * tangent / (1.0 + tangent * tangent).sqrt()).atanh()).sinh();
+        tangent * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tangent * tangent).sqrt()
+    }
+}
+
+/// Transverse Mercator `(x, y)` in metres with unit scale and no false origin.
+fn transverse_mercator_forward(latitude: f64, longitude: f64, central_meridian: f64) -> (f64, f64) {
+    let series = KruegerSeries::wgs84();
+    let longitude = (longitude - central_meridian).to_radians();
+    let conformal_tangent = series.conformal_tangent(latitude.to_radians().tan());
+
+    let xi_prime = conformal_tangent.atan2(longitude.cos());
+    let eta_prime = (longitude.sin()
+        / (conformal_tangent * conformal_tangent + longitude.cos() * longitude.cos()).sqrt())
+    .asinh();
+    let (mut xi, mut eta) = (xi_prime, eta_prime);
+    for (index, alpha) in series.alpha.iter().enumerate() {
+        let multiple = 2.0 * (index + 1) as f64;
+        xi += alpha * (multiple * xi_prime).sin() * (multiple * eta_prime).cosh();
+        eta += alpha * (multiple * xi_prime).cos() * (multiple * eta_prime).sinh();
+    }
+    (series.a * eta, series.a * xi)
+}
+
+/// Inverse of `transverse_mercator_forward`, returning `(latitude, longitude)`.
+fn transverse_mercator_inverse(x: f64, y: f64, central_meridian: f64) -> (f64, f64) {
+    let series = KruegerSeries::wgs84();
+    let (xi, eta) = (y / series.a, x / series.a);
+    let (mut xi_prime, mut eta_prime) = (xi, eta);
+    for (index, beta) in series.beta.iter().enumerate() {
+        let multiple = 2.0 * (index + 1) as f64;
+        xi_prime -= beta * (multiple * xi).sin() * (multiple * eta).cosh();
+        eta_prime -= beta * (multiple * xi).cos() * (multiple * eta).sinh();
+    }
+
+    let conformal_tangent = xi_prime.sin()
+        / (eta_prime.sinh() * eta_prime.sinh() + xi_prime.cos() * xi_prime.cos()).sqrt();
+    // Newton's method for the geodetic latitude, converging in a few iterations.
+    let e2 = series.e * series.e;
+    let mut tangent = conformal_tangent;
+    for _ in 0..10 {
+        let tangent_prime = series.conformal_tangent(tangent);
+        let delta = (conformal_tangent - tangent_prime)
+            / (1.0 + tangent_prime * tangent_prime).sqrt()
+            * (1.0 + (1.0 - e2) * tangent * tangent)
+            / ((1.0 - e2) * (1.0 + tangent * tangent).sqrt());
+        tangent += delta;
+        if delta.abs() < 1e-14 {
+            break;
+        }
+    }
+
+    (
+        tangent.atan().to_degrees(),
+        central_meridian + eta_prime.sinh().atan2(xi_prime.cos()).to_degrees(),
+    )
+}
+
+/// The `q` term of the authalic latitude for eccentricity `e` and `latitude` in radians.
+fn authalic_q(e: f64, latitude: f64) -> f64 {
+    let sine = latitude.sin();
+    (1.0 - e * e)
+        * (sine / (1.0 - e * e * sine * sine)
+            - 1.0 / (2.0 * e) * ((1.0 - e * sine) / (1.0 + e * sine)).ln())
+}
+
+/// Ellipsoidal Lambert azimuthal equal-area projection, EPSG method 9820.
+struct LambertAzimuthalEqualArea {
+    e: f64,
+    central_longitude: f64,
+    false_easting: f64,
+    false_northing: f64,
+    /// Authalic latitude of the origin.
+    beta_origin: f64,
+    /// `q` at the pole.
+    q_pole: f64,
+    /// Radius of the authalic sphere.
+    r_q: f64,
+    d: f64,
+}
+
+impl LambertAzimuthalEqualArea {
+    fn europe() -> Self {
+        let f = GRS80_FLATTENING;
+        let e = (f * (2.0 - f)).sqrt();
+        let origin_latitude = 52f64.to_radians();
+        let q_pole = authalic_q(e, 90f64.to_radians());
+        let beta_origin = (authalic_q(e, origin_latitude) / q_pole).asin();
+        let r_q = SEMI_MAJOR_AXIS * (q_pole / 2.0).sqrt();
+        let d = SEMI_MAJOR_AXIS * origin_latitude.cos()
+            / (1.0 - e * e * origin_latitude.sin().powi(2)).sqrt()
+            / (r_q * beta_origin.cos());
+        LambertAzimuthalEqualArea {
+            e,
+            central_longitude: 10f64.to_radians(),
+            false_easting: 4321000.0,
+            false_northing: 3210000.0,
+            beta_origin,
+            q_pole,
+            r_q,
+            d,
+        }
+    }
+
+    fn forward(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let beta = (authalic_q(self.e, latitude.to_radians()) / self.q_pole)
+            .clamp(-1.0, 1.0)
+            .asin();
+        let longitude = longitude.to_radians() - self.central_longitude;
+        let b = self.r_q
+            * (2.0
+                / (1.0
+                    + self.beta_origin.sin() * beta.sin()
+                    + self.beta_origin.cos() * beta.cos() * longitude.cos()))
+            .sqrt();
+        (
+            self.false_easting + b * self.d * beta.cos() * longitude.sin(),
+            self.false_northing
+                + b / self.d
+                    * (self.beta_origin.cos() * beta.sin()
+                        - self.beta_origin.sin() * beta.cos() * longitude.cos()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let (easting, northing) = (x - self.false_easting, y - self.false_northing);
+        let rho = ((easting / self.d).powi(2) + (self.d * northing).powi(2)).sqrt();
+        if rho == 0.0 {
+            return (52.0, self.central_longitude.to_degrees());
+        }
+        let c = 2.0 * (rho / (2.0 * self.r_q)).asin();
+        let beta = (c.cos() * self.beta_origin.sin()
+            + self.d * northing * c.sin() * self.beta_origin.cos() / rho)
+            .asin();
+        let longitude = self.central_longitude
+            + (easting * c.sin()).atan2(
+                self.d * rho * self.beta_origin.cos() * c.cos()
+                    - self.d * self.d * northing * self.beta_origin.sin() * c.sin(),
+            );
+
+        let (e2, e4, e6) = (self.e.powi(2), self.e.powi(4), self.e.powi(6));
+        let latitude = beta
+            + (e2 / 3.0 + 31.0 * e4 / 180.0 + 517.0 * e6 / 5040.0) * (2.0 * beta).sin()
+            + (23.0 * e4 / 360.0 + 251.0 * e6 / 3780.0) * (4.0 * beta).sin()
+            + 761.0 * e6 / 45360.0 * (6.0 * beta).sin();
+        (latitude.to_degrees(), longitude.to_degrees())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projects_and_unprojects_coordinates() {
+        let cases = [
+            (Crs::LaeaEurope, (50.0, 5.0), (3962799.45, 2999718.85)),
+            (Crs::WebMercator, (0.0, 180.0), (20037508.342789244, 0.0)),
+            (
+                Crs::Utm {
+                    zone: 31,
+                    north: true,
+                },
+                (0.0, 3.0),
+                (500000.0, 0.0),
+            ),
+        ];
+        for (crs, (latitude, longitude), (x, y)) in cases {
+            let (projected_x, projected_y) = crs.from_wgs84(latitude, longitude);
+            assert!((projected_x - x).abs() < 0.01, "{crs:?} {projected_x}");
+            assert!((projected_y - y).abs() < 0.01, "{crs:?} {projected_y}");
+
+            let (unprojected_latitude, unprojected_longitude) = crs.to_wgs84(x, y);
+            assert!(
+                (unprojected_latitude - latitude).abs() < 1e-6,
+                "{crs:?} {unprojected_latitude}"
+            );
+            assert!(
+                (unprojected_longitude - longitude).abs() < 1e-6,
+                "{crs:?} {unprojected_longitude}"
+            );
+            let (round_trip_latitude, round_trip_longitude) =
+                crs.to_wgs84(projected_x, projected_y);
+            assert!((round_trip_latitude - latitude).abs() < 1e-7, "{crs:?}");
+            assert!((round_trip_longitude - longitude).abs() < 1e-7, "{crs:?}");
+        }
+    }
+}
@@ -0,0 +1,575 @@
+use std::sync::Arc;
+
+use polars::prelude::*;
+
+use crate::binary_hash_scheme::BinaryHashScheme;
+use crate::cell_id::CellId;
+use crate::crs::Crs;
+use crate::invalid_coordinate_policy::InvalidCoordinatePolicy;
+use crate::overflow_policy::OverflowPolicy;
+use crate::spatial_scheme::SpatialScheme;
+use crate::tile_assignment::TileAssignment;
+use crate::tiler::Tiler;
+use crate::tiler_error::TilerError;
+
+pub fn binary_hash_expr(latitude: Expr, longitude: Expr, binary_hash_precision: u8) -> Expr {
+    cell_expr(
+        latitude,
+        longitude,
+        BinaryHashScheme::default(),
+        binary_hash_precision,
+    )
+}
+
+pub fn cell_expr<S>(latitude: Expr, longitude: Expr, spatial_scheme: S, precision: u8) -> Expr
+where
+    S: SpatialScheme + Send + Sync + 'static,
+{
+    map_multiple(
+        move |columns: &mut [Series]| {
+            map_coordinates::<_, Utf8Chunked>(&columns[0], &columns[1], |latitude, longitude| {
+                Some(spatial_scheme.token(spatial_scheme.encode(latitude, longitude, precision)))
+            })
+            .map(Some)
+        },
+        [latitude, longitude],
+        GetOutput::from_type(DataType::Utf8),
+    )
+}
+
+/// Like `cell_expr`, but yields the packed `CellId` of each coordinate as a `u64`.
+pub fn cell_id_expr<S>(latitude: Expr, longitude: Expr, spatial_scheme: S, precision: u8) -> Expr
+where
+    S: SpatialScheme + Send + Sync + 'static,
+{
+    map_multiple(
+        move |columns: &mut [Series]| {
+            map_coordinates::<_, UInt64Chunked>(&columns[0], &columns[1], |latitude, longitude| {
+                Some(spatial_scheme.encode(latitude, longitude, precision).0)
+            })
+            .map(Some)
+        },
+        [latitude, longitude],
+        GetOutput::from_type(DataType::UInt64),
+    )
+}
+
+/// The tile id of each coordinate, with `latitude` as y and `longitude` as x in `input_crs`.
+pub fn tile_id_expr<S>(
+    latitude: Expr,
+    longitude: Expr,
+    tile_assignment: TileAssignment<S>,
+    input_crs: Crs,
+) -> Expr
+where
+    S: SpatialScheme + Send + Sync + 'static,
+{
+    let tile_assignment = Arc::new(tile_assignment);
+    map_multiple(
+        move |columns: &mut [Series]| {
+            map_coordinates::<_, Utf8Chunked>(&columns[0], &columns[1], |latitude, longitude| {
+                tile_assignment
+                    .tile_for_projected(&input_crs, longitude, latitude)
+                    .map(|binary_hash| binary_hash.to_string())
+            })
+            .map(Some)
+        },
+        [latitude, longitude],
+        GetOutput::from_type(DataType::Utf8),
+    )
+}
+
+pub fn lazyframe_with_tile_id<S>(
+    lazyframe: LazyFrame,
+    tile_assignment: TileAssignment<S>,
+    input_crs: Crs,
+    latitude_column: &str,
+    longitude_column: &str,
+) -> LazyFrame
+where
+    S: SpatialScheme + Send + Sync + 'static,
+{
+    lazyframe.with_column(
+        tile_id_expr(
+            col(latitude_column),
+            col(longitude_column),
+            tile_assignment,
+            input_crs,
+        )
+        .alias("tile_id"),
+    )
+}
+
+pub fn dataframe_with_tile_id<S>(
+    dataframe: &DataFrame,
+    tile_assignment: TileAssignment<S>,
+    input_crs: Crs,
+    latitude_column: &str,
+    longitude_column: &str,
+) -> Result<DataFrame, TilerError>
+where
+    S: SpatialScheme + Send + Sync + 'static,
+{
+    Ok(lazyframe_with_tile_id(
+        dataframe.clone().lazy(),
+        tile_assignment,
+        input_crs,
+        latitude_column,
+        longitude_column,
+    )
+    .collect()?)
+}
+
+impl<S: SpatialScheme> Tiler<S> {
+    pub fn add_dataframe(
+        &mut self,
+        dataframe: &DataFrame,
+        latitude_column: &str,
+        longitude_column: &str,
+    ) -> Result<(), TilerError>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        self.add_lazyframe(dataframe.clone().lazy(), latitude_column, longitude_column)
+    }
+
+    /// Adds every row with both coordinates set, with the latitude column as y and the longitude
+    /// column as x in `input_crs`, applying `invalid_coordinate_policy` like
+    /// `add_projected_coordinate`. Rows are transformed, validated, encoded and counted per cell
+    /// by polars, so only one row per cell is collected. Nothing is added if a row is rejected.
+    pub fn add_lazyframe(
+        &mut self,
+        lazyframe: LazyFrame,
+        latitude_column: &str,
+        longitude_column: &str,
+    ) -> Result<(), TilerError>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let coordinates = lazyframe
+            .filter(
+                col(latitude_column)
+                    .is_not_null()
+                    .and(col(longitude_column).is_not_null()),
+            )
+            .select([
+                col(latitude_column)
+                    .cast(DataType::Float64)
+                    .alias("latitude"),
+                col(longitude_column)
+                    .cast(DataType::Float64)
+                    .alias("longitude"),
+            ]);
+        self.input_crs.validate()?;
+        let scheme_crs = self.spatial_scheme.crs();
+        let coordinates = if self.input_crs == scheme_crs {
+            coordinates
+        } else {
+            coordinates.with_columns([
+                transform_expr(self.input_crs, scheme_crs, true).alias("latitude"),
+                transform_expr(self.input_crs, scheme_crs, false).alias("longitude"),
+            ])
+        };
+
+        let extent = self.spatial_scheme.extent();
+        let is_valid = col("latitude")
+            .gt_eq(lit(extent.min_lat))
+            .and(col("latitude").lt_eq(lit(extent.max_lat)))
+            .and(col("longitude").gt_eq(lit(extent.min_lon)))
+            .and(col("longitude").lt_eq(lit(extent.max_lon)));
+        let coordinates = match self.invalid_coordinate_policy {
+            InvalidCoordinatePolicy::Reject => {
+                first_coordinate(coordinates.clone().filter(is_valid.not()))?;
+                coordinates
+            }
+            // Skipped rows are kept with a null cell, so they are counted in the same pass.
+            InvalidCoordinatePolicy::Skip => coordinates.with_column(
+                when(is_valid)
+                    .then(col("latitude"))
+                    .otherwise(lit(NULL).cast(DataType::Float64))
+                    .alias("latitude"),
+            ),
+            InvalidCoordinatePolicy::Clamp => {
+                first_coordinate(
+                    coordinates
+                        .clone()
+                        .filter(col("latitude").is_nan().or(col("longitude").is_nan())),
+                )?;
+                coordinates.with_columns([
+                    clamp_expr("latitude", extent.min_lat, extent.max_lat),
+                    clamp_expr("longitude", extent.min_lon, extent.max_lon),
+                ])
+            }
+        };
+
+        if let OverflowPolicy::Refine { .. } = self.overflow_policy {
+            let coordinate_df = coordinates
+                .clone()
+                .filter(col("latitude").is_not_null())
+                .collect()?;
+            for (latitude, longitude) in coordinate_df
+                .column("latitude")?
+                .f64()?
+                .into_no_null_iter()
+                .zip(
+                    coordinate_df
+                        .column("longitude")?
+                        .f64()?
+                        .into_no_null_iter(),
+                )
+            {
+                self.retained_coordinates
+                    .entry(self.spatial_scheme.encode(
+                        latitude,
+                        longitude,
+                        self.binary_hash_precision,
+                    ))
+                    .or_default()
+                    .push((latitude, longitude, 1.0));
+            }
+        }
+
+        let binary_hash_count_df = coordinates
+            .select([cell_id_expr(
+                col("latitude"),
+                col("longitude"),
+                self.spatial_scheme.clone(),
+                self.binary_hash_precision,
+            )
+            .alias("binary_hash")])
+            .group_by([col("binary_hash")])
+            .agg([count().cast(DataType::Int64).alias("node_count")])
+            .collect()?;
+
+        let binary_hash_list = binary_hash_count_df.column("binary_hash")?.u64()?;
+        let node_count_list = binary_hash_count_df.column("node_count")?.i64()?;
+        for (binary_hash, node_count) in binary_hash_list
+            .into_iter()
+            .zip(node_count_list.into_no_null_iter())
+        {
+            match binary_hash {
+                Some(binary_hash) => {
+                    self.increment_binary_hash(CellId(binary_hash), node_count, node_count as f64)
+                }
+                None => self.skipped_coordinate_count += node_count as u64,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fails with the first coordinate of `coordinates`, if there is any.
+fn first_coordinate(coordinates: LazyFrame) -> Result<(), TilerError> {
+    let coordinate_df = coordinates.limit(1).collect()?;
+    if coordinate_df.height() == 0 {
+        return Ok(());
+    }
+    if let (Some(latitude), Some(longitude)) = (
+        coordinate_df.column("latitude")?.f64()?.get(0),
+        coordinate_df.column("longitude")?.f64()?.get(0),
+    ) {
+        return Err(TilerError::InvalidCoordinate {
+            latitude,
+            longitude,
+        });
+    }
+    Ok(())
+}
+
+/// Clamps `column` into `min..=max`, keeping NaN.
+/// The `latitude` (if `is_latitude`) or `longitude` column, as y or x in `crs` transformed to
+/// `target_crs`.
+fn transform_expr(crs: Crs, target_crs: Crs, is_latitude: bool) -> Expr {
+    map_multiple(
+        move |columns: &mut [Series]| {
+            map_coordinates::<_, Float64Chunked>(&columns[0], &columns[1], |latitude, longitude| {
+                let (x, y) = crs.transform(&target_crs, longitude, latitude);
+                Some(if is_latitude { y } else { x })
+            })
+            .map(Some)
+        },
+        [col("latitude"), col("longitude")],
+        GetOutput::from_type(DataType::Float64),
+    )
+}
+
+fn clamp_expr(column: &str, min: f64, max: f64) -> Expr {
+    when(col(column).lt(lit(min)))
+        .then(lit(min))
+        .when(col(column).gt(lit(max)))
+        .then(lit(max))
+        .otherwise(col(column))
+        .alias(column)
+}
+
+fn map_coordinates<T, C>(
+    latitude: &Series,
+    longitude: &Series,
+    function: impl Fn(f64, f64) -> Option<T>,
+) -> Result<Series, PolarsError>
+where
+    C: FromIterator<Option<T>> + IntoSeries,
+{
+    let latitude_values = latitude.cast(&DataType::Float64)?;
+    let longitude_values = longitude.cast(&DataType::Float64)?;
+
+    let values: C = latitude_values
+        .f64()?
+        .into_iter()
+        .zip(longitude_values.f64()?)
+        .map(|(latitude, longitude)| function(latitude?, longitude?))
+        .collect();
+
+    let mut series = values.into_series();
+    series.rename(latitude.name());
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::test_support::{cell_counts_per_level, lcg};
+    use crate::tiler_error::TilerError;
+
+    #[test]
+    fn tile_ids_match_tiler_counts() {
+        let dataframe = df!(
+            "lat" => [1.0, 1.0, -1.0, 1.0, 60.0],
+            "lon" => [1.0, 2.0, 2.0, -1.0, -100.0]
+        )
+        .unwrap();
+
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        tiler.add_dataframe(&dataframe, "lat", "lon").unwrap();
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+
+        let tiled_dataframe = dataframe_with_tile_id(
+            &dataframe,
+            TileAssignment::new(&binary_hash_tiles),
+            Crs::Wgs84,
+            "lat",
+            "lon",
+        )
+        .unwrap();
+        let tile_ids: Vec<&str> = tiled_dataframe
+            .column("tile_id")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(tile_ids, vec!["11", "11", "10", "0", "0"]);
+
+        let tile_id_counts = tiled_dataframe
+            .lazy()
+            .group_by([col("tile_id")])
+            .agg([count().cast(DataType::Int64).alias("node_count")])
+            .collect()
+            .unwrap();
+        for (tile_id, node_count) in tile_id_counts
+            .column("tile_id")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .zip(
+                tile_id_counts
+                    .column("node_count")
+                    .unwrap()
+                    .i64()
+                    .unwrap()
+                    .into_no_null_iter(),
+            )
+        {
+            assert_eq!(binary_hash_tiles[tile_id].node_count, node_count);
+        }
+
+        let root_extent = geohashrust::BoundingBox::from_coordinates(48.1, 48.3, 16.2, 16.6);
+        let dataframe = df!(
+            "lat" => [48.21, 48.2, 48.15, 48.25],
+            "lon" => [16.37, 16.38, 16.5, 16.55]
+        )
+        .unwrap();
+        let mut tiler = Tiler::with_root_extent(root_extent, 12, 2).unwrap();
+        tiler.add_dataframe(&dataframe, "lat", "lon").unwrap();
+        let tile_assignment =
+            TileAssignment::with_scheme(tiler.spatial_scheme, &tiler.get_tiles().unwrap());
+        let tiled_dataframe =
+            dataframe_with_tile_id(&dataframe, tile_assignment, Crs::Wgs84, "lat", "lon").unwrap();
+        let tile_ids: Vec<&str> = tiled_dataframe
+            .column("tile_id")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(tile_ids, vec!["0", "0", "1", "1"]);
+    }
+
+    #[test]
+    fn add_dataframe_matches_add_coordinate() {
+        let latitudes = [1.0, 1.0, 2.0, 4.0, 1.5, -30.0];
+        let longitudes = [1.0, 2.0, 3.0, 1.0, 1.5, 100.0];
+
+        let mut coordinate_tiler = Tiler::new(11, 2).unwrap();
+        for (latitude, longitude) in latitudes.into_iter().zip(longitudes) {
+            coordinate_tiler
+                .add_coordinate(latitude, longitude)
+                .unwrap();
+        }
+
+        let dataframe = df!(
+            "lat" => latitudes,
+            "lon" => longitudes
+        )
+        .unwrap();
+        let mut dataframe_tiler = Tiler::new(11, 2).unwrap();
+        dataframe_tiler
+            .add_dataframe(&dataframe, "lat", "lon")
+            .unwrap();
+
+        assert_eq!(
+            dataframe_tiler.binary_hash_count,
+            coordinate_tiler.binary_hash_count
+        );
+        assert_eq!(
+            dataframe_tiler.get_tiles().unwrap(),
+            coordinate_tiler.get_tiles().unwrap()
+        );
+
+        // Columns are read in `input_crs`, like `add_projected_coordinate` and `add_parquet`.
+        let crs = Crs::Utm {
+            zone: 33,
+            north: true,
+        };
+        let (xs, ys): (Vec<f64>, Vec<f64>) = [(48.21, 16.37), (47.07, 15.44), (46.62, 14.31)]
+            .iter()
+            .map(|(latitude, longitude)| crs.from_wgs84(*latitude, *longitude))
+            .unzip();
+        let mut coordinate_tiler = Tiler::new(20, 1).unwrap();
+        coordinate_tiler.input_crs = crs;
+        for (x, y) in xs.iter().zip(&ys) {
+            coordinate_tiler.add_projected_coordinate(*x, *y).unwrap();
+        }
+        let dataframe = df!(
+            "y" => &ys,
+            "x" => &xs
+        )
+        .unwrap();
+        let mut dataframe_tiler = Tiler::new(20, 1).unwrap();
+        dataframe_tiler.input_crs = crs;
+        dataframe_tiler.add_dataframe(&dataframe, "y", "x").unwrap();
+        assert_eq!(
+            dataframe_tiler.binary_hash_count,
+            coordinate_tiler.binary_hash_count
+        );
+
+        let binary_hash_tiles = dataframe_tiler.get_tiles().unwrap();
+        let tiled_dataframe = dataframe_with_tile_id(
+            &dataframe,
+            TileAssignment::new(&binary_hash_tiles),
+            crs,
+            "y",
+            "x",
+        )
+        .unwrap();
+        let tile_ids: Vec<&str> = tiled_dataframe
+            .column("tile_id")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(tile_ids.len(), 3);
+        assert!(tile_ids
+            .iter()
+            .all(|tile_id| binary_hash_tiles[*tile_id].node_count == 1));
+    }
+
+    #[test]
+    fn add_lazyframe_applies_invalid_coordinate_policy() {
+        let dataframe = df!(
+            "lat" => [Some(1.0), Some(95.0), Some(f64::NAN), None, Some(1.0)],
+            "lon" => [Some(1.0), Some(200.0), Some(1.0), Some(1.0), Some(1.0)]
+        )
+        .unwrap();
+
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        assert!(matches!(
+            tiler.add_dataframe(&dataframe, "lat", "lon"),
+            Err(TilerError::InvalidCoordinate { latitude, .. }) if latitude == 95.0
+        ));
+        assert!(tiler.binary_hash_count.is_empty());
+
+        tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Skip;
+        tiler.add_dataframe(&dataframe, "lat", "lon").unwrap();
+        assert_eq!(tiler.skipped_coordinate_count, 2);
+        assert_eq!(
+            tiler.binary_hash_count,
+            HashMap::from([(BinaryHashScheme::default().encode(1.0, 1.0, 11), 2)])
+        );
+
+        let mut clamp_tiler = Tiler::new(11, 2).unwrap();
+        clamp_tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Clamp;
+        assert!(clamp_tiler.add_dataframe(&dataframe, "lat", "lon").is_err());
+        clamp_tiler
+            .add_dataframe(&dataframe.slice(0, 2), "lat", "lon")
+            .unwrap();
+        assert_eq!(
+            clamp_tiler.binary_hash_count[&BinaryHashScheme::default().encode(90.0, 180.0, 11)],
+            1
+        );
+    }
+
+    #[test]
+    fn single_pass_tiling_matches_per_level_tiling() {
+        fn check<S: SpatialScheme>(mut tiler: Tiler<S>) {
+            // Coordinates outside of a projected CRS are left out.
+            tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Skip;
+            // A fixed linear congruential sequence, dense around a few centres.
+            let mut next = lcg(42);
+            for index in 0..2000 {
+                let (latitude, longitude) = match index % 3 {
+                    0 => (48.2 + next() * 0.1, 16.3 + next() * 0.1),
+                    1 => (78.0 + next() * 2.0, 10.0 + next() * 10.0),
+                    _ => (next() * 160.0 - 80.0, next() * 360.0 - 180.0),
+                };
+                let (x, y) = tiler.input_crs.from_wgs84(latitude, longitude);
+                tiler
+                    .add_projected_weighted_coordinate(x, y, (index % 4) as f64)
+                    .unwrap();
+            }
+
+            let cell_counts: HashMap<CellId, (i64, f64)> = tiler
+                .get_cell_tiles()
+                .unwrap()
+                .into_iter()
+                .map(|(cell, binary_hash_tile)| {
+                    (cell, (binary_hash_tile.node_count, binary_hash_tile.weight))
+                })
+                .collect();
+            assert_eq!(cell_counts, cell_counts_per_level(&tiler).unwrap());
+        }
+
+        check(Tiler::new(30, 40).unwrap());
+        check(Tiler::new(8, 40).unwrap());
+        check(Tiler::quadkey(14, 25).unwrap());
+        #[cfg(feature = "h3")]
+        check(Tiler::h3(8, 25).unwrap());
+        check(Tiler::s2(16, 25).unwrap());
+        check(
+            Tiler::projected(
+                Crs::Utm {
+                    zone: 33,
+                    north: true,
+                },
+                30,
+                40,
+            )
+            .unwrap(),
+        );
+    }
+}
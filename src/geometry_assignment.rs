@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+
+use geo::{BoundingRect, Centroid, Geometry, Intersects, Point, Polygon};
+
+use crate::cell_id::CellId;
+use crate::spatial_scheme::SpatialScheme;
+
+/// Strategy used by `Tiler::add_geometry` to decide which cells a geometry is counted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeometryAssignment {
+    /// Count the geometry once, in the cell containing its centroid.
+    Centroid,
+    /// Count the geometry in every cell touched by its bounding box.
+    BoundingBox,
+    /// Count the geometry in every cell it actually intersects.
+    Intersection,
+}
+
+impl GeometryAssignment {
+    /// The single point `geometry` is counted at: a point geometry itself, or the centroid of any
+    /// geometry with `Centroid`. Returns `None` when the geometry is counted by `cells` instead.
+    pub(crate) fn point(&self, geometry: &Geometry<f64>) -> Option<Point<f64>> {
+        match (geometry, self) {
+            (Geometry::Point(point), _) => Some(*point),
+            (_, GeometryAssignment::Centroid) => geometry.centroid(),
+            _ => None,
+        }
+    }
+
+    /// The cells touched by a geometry that has no `point`.
+    pub(crate) fn cells<S: SpatialScheme>(
+        &self,
+        spatial_scheme: &S,
+        geometry: &Geometry<f64>,
+        precision: u8,
+    ) -> HashSet<CellId> {
+        let mut cells = HashSet::new();
+
+        match self {
+            // Empty geometries have no centroid and are not counted.
+            GeometryAssignment::Centroid => {}
+            GeometryAssignment::BoundingBox => {
+                if let Some(bounding_rect) = geometry.bounding_rect() {
+                    for root_cell in spatial_scheme.root_cells() {
+                        collect_cells(
+                            spatial_scheme,
+                            &|cell: &Polygon<f64>| cell.intersects(&bounding_rect),
+                            root_cell,
+                            precision,
+                            &mut cells,
+                        );
+                    }
+                }
+            }
+            GeometryAssignment::Intersection => {
+                for root_cell in spatial_scheme.root_cells() {
+                    collect_cells(
+                        spatial_scheme,
+                        &|cell: &Polygon<f64>| geometry.intersects(cell),
+                        root_cell,
+                        precision,
+                        &mut cells,
+                    );
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+fn collect_cells<S: SpatialScheme>(
+    spatial_scheme: &S,
+    touches: &dyn Fn(&Polygon<f64>) -> bool,
+    cell: CellId,
+    precision: u8,
+    cells: &mut HashSet<CellId>,
+) {
+    if !touches(&spatial_scheme.polygon(cell)) {
+        return;
+    }
+    if spatial_scheme.precision(cell) >= precision {
+        cells.insert(cell);
+        return;
+    }
+
+    for child_cell in spatial_scheme.children(cell) {
+        collect_cells(spatial_scheme, touches, child_cell, precision, cells);
+    }
+}
@@ -0,0 +1,580 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, BinaryArray, BooleanArray, Float64Array, LargeBinaryArray};
+use arrow::compute::{cast, filter_record_batch};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use geo::{BoundingRect, Centroid};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::metadata::KeyValue;
+use serde_json::{json, Value};
+
+use crate::crs::Crs;
+use crate::spatial_scheme::SpatialScheme;
+use crate::tile_assignment::TileAssignment;
+use crate::tiler_error::TilerError;
+use crate::wkb::{read_wkb, write_wkb_point, WkbGeometry};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeometryColumn {
+    /// A WKB encoded geometry column, as found in GeoParquet files. Rows are assigned by centroid.
+    Wkb(String),
+    /// Plain latitude and longitude columns. A WKB point column named `geometry` is added to the output.
+    Coordinates {
+        latitude_column: String,
+        longitude_column: String,
+    },
+}
+
+pub struct GeoParquetWriter {
+    pub geometry_column: GeometryColumn,
+    /// The CRS of the input geometries, transformed to the CRS of the tiles' spatial scheme to
+    /// assign rows.
+    pub input_crs: Crs,
+    pub max_open_files: usize,
+    pub batch_size: usize,
+}
+
+struct TileFileWriter {
+    arrow_writer: ArrowWriter<File>,
+    bbox: Option<[f64; 4]>,
+    geometry_types: BTreeSet<String>,
+}
+
+impl GeoParquetWriter {
+    pub fn new(geometry_column: GeometryColumn) -> Self {
+        GeoParquetWriter {
+            geometry_column,
+            input_crs: Crs::default(),
+            max_open_files: 64,
+            batch_size: 65536,
+        }
+    }
+
+    /// Writes one `<binary_hash>.parquet` file per tile of `tile_assignment` into `output_directory`.
+    ///
+    /// At most `max_open_files` output files are open at once: tiles are written in groups of that size,
+    /// reading the input once per group. Rows that fall outside every tile are skipped.
+    pub fn write<S: SpatialScheme>(
+        &self,
+        tile_assignment: &TileAssignment<S>,
+        input_path: &Path,
+        output_directory: &Path,
+    ) -> Result<HashMap<String, PathBuf>, TilerError> {
+        if self.max_open_files == 0 {
+            return Err(TilerError::InvalidConfiguration(String::from(
+                "max_open_files must be at least 1",
+            )));
+        }
+        fs::create_dir_all(output_directory)?;
+
+        let mut binary_hashes: Vec<&str> = tile_assignment.tile_ids().collect();
+        binary_hashes.sort();
+
+        let mut output_paths = HashMap::new();
+        for binary_hash_group in binary_hashes.chunks(self.max_open_files) {
+            let group_output_paths = self.write_group(
+                tile_assignment,
+                binary_hash_group,
+                input_path,
+                output_directory,
+            )?;
+            output_paths.extend(group_output_paths);
+        }
+
+        Ok(output_paths)
+    }
+
+    fn write_group<S: SpatialScheme>(
+        &self,
+        tile_assignment: &TileAssignment<S>,
+        binary_hash_group: &[&str],
+        input_path: &Path,
+        output_directory: &Path,
+    ) -> Result<HashMap<String, PathBuf>, ParquetError> {
+        let reader_builder = ParquetRecordBatchReaderBuilder::try_new(File::open(input_path)?)?;
+        let input_geo_metadata = reader_builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .and_then(|key_values| key_values.iter().find(|key_value| key_value.key == "geo"))
+            .and_then(|key_value| key_value.value.as_deref())
+            .and_then(|value| serde_json::from_str::<Value>(value).ok());
+        let output_schema = self.output_schema(reader_builder.schema())?;
+        let record_batch_reader = reader_builder.with_batch_size(self.batch_size).build()?;
+
+        let binary_hash_group: HashSet<&str> = binary_hash_group.iter().copied().collect();
+        let mut tile_file_writers: HashMap<&str, TileFileWriter> = HashMap::new();
+        for record_batch in record_batch_reader {
+            let record_batch = self.with_geometry_column(record_batch?, &output_schema)?;
+            let geometries = read_wkb_column(&record_batch, self.geometry_column_name())?;
+
+            let mut row_binary_hashes: HashMap<&str, Vec<bool>> = HashMap::new();
+            for (row, geometry) in geometries.iter().enumerate() {
+                let Some(wkb_geometry) = geometry else {
+                    continue;
+                };
+                let Some(centroid) = wkb_geometry.geometry.centroid() else {
+                    continue;
+                };
+                let Some(binary_hash) =
+                    tile_assignment.tile_for_projected(&self.input_crs, centroid.x(), centroid.y())
+                else {
+                    continue;
+                };
+                let Some(binary_hash) = binary_hash_group.get(binary_hash) else {
+                    continue;
+                };
+                row_binary_hashes
+                    .entry(binary_hash)
+                    .or_insert_with(|| vec![false; record_batch.num_rows()])[row] = true;
+            }
+
+            for (binary_hash, rows) in row_binary_hashes {
+                let tile_file_writer = match tile_file_writers.get_mut(binary_hash) {
+                    Some(tile_file_writer) => tile_file_writer,
+                    None => {
+                        let output_file =
+                            File::create(tile_output_path(output_directory, binary_hash))?;
+                        let arrow_writer =
+                            ArrowWriter::try_new(output_file, output_schema.clone(), None)?;
+                        tile_file_writers
+                            .entry(binary_hash)
+                            .or_insert(TileFileWriter {
+                                arrow_writer,
+                                bbox: None,
+                                geometry_types: BTreeSet::new(),
+                            })
+                    }
+                };
+
+                for (row, _) in rows.iter().enumerate().filter(|(_, selected)| **selected) {
+                    if let Some(wkb_geometry) = &geometries[row] {
+                        tile_file_writer.include_geometry(wkb_geometry);
+                    }
+                }
+                let tile_record_batch =
+                    filter_record_batch(&record_batch, &BooleanArray::from(rows))?;
+                tile_file_writer.arrow_writer.write(&tile_record_batch)?;
+            }
+        }
+
+        let mut output_paths = HashMap::new();
+        for (binary_hash, mut tile_file_writer) in tile_file_writers {
+            let geo_metadata = self.geo_metadata(
+                input_geo_metadata.clone(),
+                tile_file_writer.bbox,
+                &tile_file_writer.geometry_types,
+            );
+            tile_file_writer
+                .arrow_writer
+                .append_key_value_metadata(KeyValue::new(
+                    String::from("geo"),
+                    geo_metadata.to_string(),
+                ));
+            tile_file_writer.arrow_writer.close()?;
+            output_paths.insert(
+                binary_hash.to_string(),
+                tile_output_path(output_directory, binary_hash),
+            );
+        }
+
+        Ok(output_paths)
+    }
+
+    fn geometry_column_name(&self) -> &str {
+        match &self.geometry_column {
+            GeometryColumn::Wkb(geometry_column) => geometry_column,
+            GeometryColumn::Coordinates { .. } => "geometry",
+        }
+    }
+
+    fn output_schema(&self, input_schema: &SchemaRef) -> Result<SchemaRef, ParquetError> {
+        match &self.geometry_column {
+            GeometryColumn::Wkb(geometry_column) => {
+                input_schema.field_with_name(geometry_column)?;
+                Ok(input_schema.clone())
+            }
+            GeometryColumn::Coordinates {
+                latitude_column,
+                longitude_column,
+            } => {
+                input_schema.field_with_name(latitude_column)?;
+                input_schema.field_with_name(longitude_column)?;
+                if input_schema.field_with_name("geometry").is_ok() {
+                    return Err(ParquetError::General(String::from(
+                        "input already has a geometry column",
+                    )));
+                }
+                let mut fields: Vec<Field> = input_schema
+                    .fields()
+                    .iter()
+                    .map(|field| field.as_ref().clone())
+                    .collect();
+                fields.push(Field::new("geometry", DataType::Binary, true));
+                Ok(Arc::new(Schema::new_with_metadata(
+                    fields,
+                    input_schema.metadata().clone(),
+                )))
+            }
+        }
+    }
+
+    fn with_geometry_column(
+        &self,
+        record_batch: RecordBatch,
+        output_schema: &SchemaRef,
+    ) -> Result<RecordBatch, ParquetError> {
+        let GeometryColumn::Coordinates {
+            latitude_column,
+            longitude_column,
+        } = &self.geometry_column
+        else {
+            return Ok(record_batch);
+        };
+
+        let latitudes = float64_column(&record_batch, latitude_column)?;
+        let longitudes = float64_column(&record_batch, longitude_column)?;
+        let geometries: BinaryArray = latitudes
+            .iter()
+            .zip(longitudes.iter())
+            .map(|(latitude, longitude)| Some(write_wkb_point(longitude?, latitude?)))
+            .collect();
+
+        let mut columns: Vec<ArrayRef> = record_batch.columns().to_vec();
+        columns.push(Arc::new(geometries));
+        Ok(RecordBatch::try_new(output_schema.clone(), columns)?)
+    }
+
+    fn geo_metadata(
+        &self,
+        input_geo_metadata: Option<Value>,
+        bbox: Option<[f64; 4]>,
+        geometry_types: &BTreeSet<String>,
+    ) -> Value {
+        let geometry_column = self.geometry_column_name();
+        let mut geo_metadata = input_geo_metadata
+            .filter(|geo_metadata| geo_metadata["columns"][geometry_column].is_object())
+            .unwrap_or_else(|| {
+                json!({
+                    "version": "1.0.0",
+                    "primary_column": geometry_column,
+                    "columns": {
+                        geometry_column: {
+                            "encoding": "WKB",
+                        },
+                    },
+                })
+            });
+
+        let column_metadata = &mut geo_metadata["columns"][geometry_column];
+        column_metadata["geometry_types"] = json!(geometry_types);
+        // Readers take a missing crs for OGC:CRS84.
+        if self.input_crs != Crs::Wgs84 {
+            column_metadata["crs"] = json!({
+                "id": {
+                    "authority": "EPSG",
+                    "code": self.input_crs.epsg(),
+                },
+            });
+        }
+        match bbox {
+            Some(bbox) => column_metadata["bbox"] = json!(bbox),
+            None => {
+                if let Some(column_metadata) = column_metadata.as_object_mut() {
+                    column_metadata.remove("bbox");
+                }
+            }
+        }
+        geo_metadata
+    }
+}
+
+impl TileFileWriter {
+    fn include_geometry(&mut self, wkb_geometry: &WkbGeometry) {
+        self.geometry_types
+            .insert(wkb_geometry.geometry_type.clone());
+        let Some(bounding_rect) = wkb_geometry.geometry.bounding_rect() else {
+            return;
+        };
+        let geometry_bbox = [
+            bounding_rect.min().x,
+            bounding_rect.min().y,
+            bounding_rect.max().x,
+            bounding_rect.max().y,
+        ];
+        self.bbox = Some(match self.bbox {
+            Some(bbox) => [
+                bbox[0].min(geometry_bbox[0]),
+                bbox[1].min(geometry_bbox[1]),
+                bbox[2].max(geometry_bbox[2]),
+                bbox[3].max(geometry_bbox[3]),
+            ],
+            None => geometry_bbox,
+        });
+    }
+}
+
+fn tile_output_path(output_directory: &Path, binary_hash: &str) -> PathBuf {
+    output_directory.join(format!("{binary_hash}.parquet"))
+}
+
+pub(crate) fn read_wkb_column(
+    record_batch: &RecordBatch,
+    column: &str,
+) -> Result<Vec<Option<WkbGeometry>>, ParquetError> {
+    let geometry_column = record_batch
+        .column_by_name(column)
+        .ok_or_else(|| ParquetError::General(format!("missing column {column}")))?;
+
+    let wkb_values: Vec<Option<&[u8]>> = if let Some(binary_array) =
+        geometry_column.as_any().downcast_ref::<BinaryArray>()
+    {
+        binary_array.iter().collect()
+    } else if let Some(binary_array) = geometry_column.as_any().downcast_ref::<LargeBinaryArray>() {
+        binary_array.iter().collect()
+    } else {
+        return Err(ParquetError::General(format!(
+            "column {column} must be WKB encoded binary"
+        )));
+    };
+
+    wkb_values
+        .into_iter()
+        .map(|wkb_value| {
+            let Some(wkb_value) = wkb_value else {
+                return Ok(None);
+            };
+            read_wkb(wkb_value).map(Some).map_err(ParquetError::General)
+        })
+        .collect()
+}
+
+pub(crate) fn float64_column(
+    record_batch: &RecordBatch,
+    column: &str,
+) -> Result<Float64Array, ParquetError> {
+    let values = record_batch
+        .column_by_name(column)
+        .ok_or_else(|| ParquetError::General(format!("missing column {column}")))?;
+    let values = cast(values, &DataType::Float64)?;
+    values
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .cloned()
+        .ok_or_else(|| ParquetError::General(format!("column {column} is not numeric")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::Tiler;
+    use geohashrust::BoundingBox;
+
+    fn write_coordinates(input_path: &Path, latitudes: Vec<f64>, longitudes: Vec<f64>) {
+        let input_schema = Arc::new(Schema::new(vec![
+            Field::new("lat", DataType::Float64, false),
+            Field::new("lon", DataType::Float64, false),
+        ]));
+        let input_record_batch = RecordBatch::try_new(
+            input_schema.clone(),
+            vec![
+                Arc::new(Float64Array::from(latitudes)),
+                Arc::new(Float64Array::from(longitudes)),
+            ],
+        )
+        .unwrap();
+        let mut arrow_writer =
+            ArrowWriter::try_new(File::create(input_path).unwrap(), input_schema, None).unwrap();
+        arrow_writer.write(&input_record_batch).unwrap();
+        arrow_writer.close().unwrap();
+    }
+
+    fn row_count(output_path: &Path) -> usize {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(output_path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|record_batch| record_batch.unwrap().num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn writes_one_geoparquet_file_per_tile() {
+        let latitudes = vec![1.0, 1.0, -1.0, 1.0, 60.0];
+        let longitudes = vec![1.0, 2.0, 2.0, -1.0, -100.0];
+
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        for (latitude, longitude) in latitudes.iter().zip(&longitudes) {
+            tiler.add_coordinate(*latitude, *longitude).unwrap();
+        }
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+
+        let directory = tempfile::tempdir().unwrap();
+        let input_path = directory.path().join("input.parquet");
+        write_coordinates(&input_path, latitudes, longitudes);
+
+        let mut geoparquet_writer = GeoParquetWriter::new(GeometryColumn::Coordinates {
+            latitude_column: String::from("lat"),
+            longitude_column: String::from("lon"),
+        });
+        geoparquet_writer.max_open_files = 0;
+        assert!(matches!(
+            geoparquet_writer.write(
+                &TileAssignment::new(&binary_hash_tiles),
+                &input_path,
+                &directory.path().join("tiles"),
+            ),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+
+        geoparquet_writer.max_open_files = 1;
+        let output_paths = geoparquet_writer
+            .write(
+                &TileAssignment::new(&binary_hash_tiles),
+                &input_path,
+                &directory.path().join("tiles"),
+            )
+            .unwrap();
+        assert_eq!(output_paths.len(), binary_hash_tiles.len());
+
+        for (binary_hash, output_path) in output_paths {
+            let reader_builder =
+                ParquetRecordBatchReaderBuilder::try_new(File::open(output_path).unwrap()).unwrap();
+            let geo_metadata: Value = serde_json::from_str(
+                reader_builder
+                    .metadata()
+                    .file_metadata()
+                    .key_value_metadata()
+                    .unwrap()
+                    .iter()
+                    .find(|key_value| key_value.key == "geo")
+                    .unwrap()
+                    .value
+                    .as_deref()
+                    .unwrap(),
+            )
+            .unwrap();
+            let row_count: usize = reader_builder
+                .build()
+                .unwrap()
+                .map(|record_batch| record_batch.unwrap().num_rows())
+                .sum();
+
+            assert_eq!(row_count as i64, binary_hash_tiles[&binary_hash].node_count);
+            assert_eq!(geo_metadata["primary_column"], "geometry");
+            assert_eq!(
+                geo_metadata["columns"]["geometry"]["geometry_types"],
+                json!(["Point"])
+            );
+            if binary_hash == "11" {
+                assert_eq!(
+                    geo_metadata["columns"]["geometry"]["bbox"],
+                    json!([1.0, 1.0, 2.0, 1.0])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn assigns_rows_relative_to_the_root_extent() {
+        let latitudes = vec![48.21, 48.2, 48.15, 48.25];
+        let longitudes = vec![16.37, 16.38, 16.5, 16.55];
+
+        let root_extent = BoundingBox::from_coordinates(48.1, 48.3, 16.2, 16.6);
+        let mut tiler = Tiler::with_root_extent(root_extent, 12, 2).unwrap();
+        for (latitude, longitude) in latitudes.iter().zip(&longitudes) {
+            tiler.add_coordinate(*latitude, *longitude).unwrap();
+        }
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+        assert_eq!(binary_hash_tiles["0"].node_count, 2);
+
+        let directory = tempfile::tempdir().unwrap();
+        let input_path = directory.path().join("input.parquet");
+        write_coordinates(&input_path, latitudes, longitudes);
+
+        let geoparquet_writer = GeoParquetWriter::new(GeometryColumn::Coordinates {
+            latitude_column: String::from("lat"),
+            longitude_column: String::from("lon"),
+        });
+        let tile_assignment = TileAssignment::with_scheme(tiler.spatial_scheme, &binary_hash_tiles);
+        let output_paths = geoparquet_writer
+            .write(
+                &tile_assignment,
+                &input_path,
+                &directory.path().join("tiles"),
+            )
+            .unwrap();
+
+        assert_eq!(output_paths.len(), binary_hash_tiles.len());
+        for (binary_hash, output_path) in output_paths {
+            assert_eq!(
+                row_count(&output_path) as i64,
+                binary_hash_tiles[&binary_hash].node_count
+            );
+        }
+    }
+
+    #[test]
+    fn writes_the_input_crs() {
+        let crs = Crs::Utm {
+            zone: 33,
+            north: true,
+        };
+        let (x, y) = crs.from_wgs84(48.21, 16.37);
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        tiler.input_crs = crs;
+        tiler.add_projected_coordinate(x, y).unwrap();
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+
+        let directory = tempfile::tempdir().unwrap();
+        let input_path = directory.path().join("input.parquet");
+        write_coordinates(&input_path, vec![y], vec![x]);
+
+        let mut geoparquet_writer = GeoParquetWriter::new(GeometryColumn::Coordinates {
+            latitude_column: String::from("lat"),
+            longitude_column: String::from("lon"),
+        });
+        geoparquet_writer.input_crs = crs;
+        let output_paths = geoparquet_writer
+            .write(
+                &TileAssignment::new(&binary_hash_tiles),
+                &input_path,
+                &directory.path().join("tiles"),
+            )
+            .unwrap();
+        assert_eq!(output_paths.len(), 1);
+
+        let output_path = output_paths.values().next().unwrap();
+        assert_eq!(row_count(output_path), 1);
+        let reader_builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(output_path).unwrap()).unwrap();
+        let geo_metadata: Value = serde_json::from_str(
+            reader_builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .unwrap()
+                .iter()
+                .find(|key_value| key_value.key == "geo")
+                .unwrap()
+                .value
+                .as_deref()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            geo_metadata["columns"]["geometry"]["crs"],
+            json!({"id": {"authority": "EPSG", "code": 32633}})
+        );
+        assert_eq!(
+            geo_metadata["columns"]["geometry"]["bbox"],
+            json!([x, y, x, y])
+        );
+    }
+}
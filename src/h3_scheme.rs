@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use geo::{LineString, Polygon};
+use geohashrust::BoundingBox;
+use h3o::{CellIndex, LatLng, Resolution};
+
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::spatial_scheme::{ring_bounds, unwrap_antimeridian, SpatialScheme};
+
+/// Uber's H3 hexagonal grid, with cells identified by their hexadecimal index strings.
+///
+/// The precision of a cell is its H3 resolution. Resolution 0 holds the 122 base cells, so tiles
+/// may be as coarse as a base cell. As a `CellId`, a cell is its 7 base cell bits followed by the
+/// 3 bit digit of each finer resolution.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct H3Scheme;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct H3Tile {
+    pub cell_index: CellIndex,
+    pub boundary: Polygon<f64>,
+    pub node_count: i64,
+    pub weight: f64,
+}
+
+impl H3Tile {
+    pub(crate) fn from_cell_tiles(
+        cell_tiles: HashMap<CellId, BinaryHashTile>,
+    ) -> HashMap<CellIndex, H3Tile> {
+        cell_tiles
+            .into_iter()
+            .map(|(cell, binary_hash_tile)| {
+                let cell_index = cell_index(cell);
+                let h3_tile = H3Tile {
+                    cell_index,
+                    boundary: H3Scheme.polygon(cell),
+                    node_count: binary_hash_tile.node_count,
+                    weight: binary_hash_tile.weight,
+                };
+                (cell_index, h3_tile)
+            })
+            .collect()
+    }
+}
+
+const BASE_CELL_BITS: u8 = 7;
+const DIGIT_BITS: u8 = 3;
+const MAX_RESOLUTION: u8 = 15;
+
+fn cell_index(cell: CellId) -> CellIndex {
+    let resolution = (cell.level() - BASE_CELL_BITS) / DIGIT_BITS;
+    let bits = cell.bits();
+    let base_cell = bits >> (DIGIT_BITS * resolution);
+
+    // H3 index mode 1 (cell), followed by the resolution, the base cell and 15 digits where
+    // digits finer than the resolution are 7.
+    let mut index = (1 << 59) | (u64::from(resolution) << 52) | (base_cell << 45);
+    for digit_resolution in 1..=MAX_RESOLUTION {
+        let digit = if digit_resolution <= resolution {
+            (bits >> (DIGIT_BITS * (resolution - digit_resolution))) & 7
+        } else {
+            7
+        };
+        index |= digit << (DIGIT_BITS * (MAX_RESOLUTION - digit_resolution));
+    }
+    CellIndex::try_from(index).unwrap_or_else(|_| panic!("invalid H3 cell {index:x}"))
+}
+
+fn cell_id(cell_index: CellIndex) -> CellId {
+    let index = u64::from(cell_index);
+    let resolution = u8::from(cell_index.resolution());
+    let mut bits = (index >> 45) & 127;
+    for digit_resolution in 1..=resolution {
+        let digit = (index >> (DIGIT_BITS * (MAX_RESOLUTION - digit_resolution))) & 7;
+        bits = (bits << DIGIT_BITS) | digit;
+    }
+    CellId::from_bits(bits, BASE_CELL_BITS + DIGIT_BITS * resolution)
+}
+
+fn resolution(precision: u8) -> Resolution {
+    Resolution::try_from(precision).unwrap_or_else(|_| panic!("invalid H3 resolution {precision}"))
+}
+
+/// Returns the cell boundary as (longitude, latitude) pairs. Cells crossing the antimeridian are
+/// unwrapped to longitudes above 180 so that the ring stays continuous.
+fn boundary_coordinates(cell: CellId) -> Vec<(f64, f64)> {
+    let mut coordinates: Vec<(f64, f64)> = cell_index(cell)
+        .boundary()
+        .iter()
+        .map(|vertex| (vertex.lng(), vertex.lat()))
+        .collect();
+    unwrap_antimeridian(&mut coordinates);
+    coordinates
+}
+
+impl SpatialScheme for H3Scheme {
+    fn max_precision(&self) -> u8 {
+        15
+    }
+
+    fn min_precision(&self) -> u8 {
+        0
+    }
+
+    fn encode(&self, latitude: f64, longitude: f64, precision: u8) -> CellId {
+        cell_id(
+            LatLng::new(latitude, longitude)
+                .unwrap_or_else(|_| panic!("invalid coordinate ({latitude}, {longitude})"))
+                .to_cell(resolution(precision)),
+        )
+    }
+
+    fn precision(&self, cell: CellId) -> u8 {
+        (cell.level() - BASE_CELL_BITS) / DIGIT_BITS
+    }
+
+    fn parent(&self, cell: CellId, precision: u8) -> CellId {
+        cell.parent(BASE_CELL_BITS + DIGIT_BITS * precision)
+    }
+
+    fn children(&self, cell: CellId) -> Vec<CellId> {
+        let cell_index = cell_index(cell);
+        match cell_index.resolution().succ() {
+            Some(child_resolution) => cell_index.children(child_resolution).map(cell_id).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn root_cells(&self) -> Vec<CellId> {
+        CellIndex::base_cells().map(cell_id).collect()
+    }
+
+    fn bounds(&self, cell: CellId) -> BoundingBox {
+        ring_bounds(boundary_coordinates(cell))
+    }
+
+    fn polygon(&self, cell: CellId) -> Polygon<f64> {
+        Polygon::new(LineString::from(boundary_coordinates(cell)), Vec::new())
+    }
+
+    fn token(&self, cell: CellId) -> String {
+        cell_index(cell).to_string()
+    }
+
+    fn parse_token(&self, token: &str) -> CellId {
+        cell_id(
+            token
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid H3 cell index {token}")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::Tiler;
+
+    #[test]
+    fn tiles_follow_the_h3_hierarchy() {
+        let cell = H3Scheme.encode(48.2082, 16.3738, 9);
+        let vienna_cell = LatLng::new(48.2082, 16.3738)
+            .unwrap()
+            .to_cell(Resolution::Nine);
+        assert_eq!(cell_index(cell), vienna_cell);
+        assert_eq!(H3Scheme.token(cell), vienna_cell.to_string());
+        assert_eq!(H3Scheme.parse_token(&vienna_cell.to_string()), cell);
+        assert_eq!(H3Scheme.precision(cell), 9);
+        assert_eq!(
+            cell_index(H3Scheme.parent(cell, 4)),
+            vienna_cell.parent(Resolution::Four).unwrap()
+        );
+        assert_eq!(H3Scheme.children(cell).len(), 7);
+        assert_eq!(H3Scheme.root_cells().len(), 122);
+
+        let mut tiler = Tiler::h3(9, 2).unwrap();
+        tiler.add_coordinate(48.2082, 16.3738).unwrap();
+        tiler.add_coordinate(48.2083, 16.3739).unwrap();
+        tiler.add_coordinate(48.2300, 16.4000).unwrap();
+        tiler.add_coordinate(-33.8688, 151.2093).unwrap();
+
+        let h3_tiles = tiler.get_h3_tiles().unwrap();
+        assert_eq!(
+            h3_tiles
+                .values()
+                .map(|h3_tile| h3_tile.node_count)
+                .sum::<i64>(),
+            4
+        );
+        assert!(h3_tiles.values().all(|h3_tile| h3_tile.node_count <= 2));
+
+        let vienna_tile = h3_tiles
+            .values()
+            .find(|h3_tile| {
+                vienna_cell.parent(h3_tile.cell_index.resolution()) == Some(h3_tile.cell_index)
+            })
+            .unwrap();
+        assert_eq!(vienna_tile.node_count, 2);
+        assert!(u8::from(vienna_tile.cell_index.resolution()) > 0);
+        assert!(vienna_tile.boundary.exterior().0.len() >= 7);
+
+        let sydney_tile = &h3_tiles[&cell_index(H3Scheme.encode(-33.8688, 151.2093, 0))];
+        assert_eq!(sydney_tile.node_count, 1);
+    }
+}
@@ -0,0 +1,47 @@
+use geohashrust::BoundingBox;
+
+use crate::tiler_error::TilerError;
+
+/// What `Tiler` does with a coordinate that is NaN, infinite or outside the extent of its spatial
+/// scheme, the WGS84 range unless the scheme has a custom root extent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum InvalidCoordinatePolicy {
+    /// Return `TilerError::InvalidCoordinate`. Coordinates added before it are kept.
+    #[default]
+    Reject,
+    /// Leave the coordinate out and count it in `Tiler::skipped_coordinate_count`.
+    Skip,
+    /// Clamp the coordinate into the extent, such as [-90, 90] for the latitude and [-180, 180]
+    /// for the longitude. NaN cannot be clamped and is still rejected.
+    Clamp,
+}
+
+impl InvalidCoordinatePolicy {
+    /// Returns the coordinate to count, or `None` if it should be skipped.
+    pub(crate) fn apply(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        extent: &BoundingBox,
+    ) -> Result<Option<(f64, f64)>, TilerError> {
+        if (extent.min_lat..=extent.max_lat).contains(&latitude)
+            && (extent.min_lon..=extent.max_lon).contains(&longitude)
+        {
+            return Ok(Some((latitude, longitude)));
+        }
+
+        match self {
+            InvalidCoordinatePolicy::Skip => Ok(None),
+            InvalidCoordinatePolicy::Clamp if !latitude.is_nan() && !longitude.is_nan() => {
+                Ok(Some((
+                    latitude.clamp(extent.min_lat, extent.max_lat),
+                    longitude.clamp(extent.min_lon, extent.max_lon),
+                )))
+            }
+            _ => Err(TilerError::InvalidCoordinate {
+                latitude,
+                longitude,
+            }),
+        }
+    }
+}
@@ -1,5 +1,61 @@
+mod balanced_tiles;
+mod binary_hash_scheme;
 mod binary_hash_tile;
+mod cell_id;
+mod crs;
+#[cfg(feature = "polars")]
+mod dataframe;
+mod geometry_assignment;
+#[cfg(feature = "parquet")]
+mod geoparquet;
+#[cfg(feature = "h3")]
+mod h3_scheme;
+mod invalid_coordinate_policy;
+mod merged_tile;
+mod overflow_policy;
+mod quadkey_scheme;
+mod s2_scheme;
+mod spatial_scheme;
+mod split_mode;
+mod tile_assignment;
+mod tile_changes;
+mod tile_geojson;
 mod tiler;
+mod tiler_error;
+#[cfg(feature = "parquet")]
+mod wkb;
 
+// Lets the helpers shared with the benchmarks name this crate as they do from outside.
+#[cfg(test)]
+extern crate self as geo_data_tiler;
+#[cfg(test)]
+#[path = "../benches/support/mod.rs"]
+mod test_support;
+
+pub use crate::balanced_tiles::BalancedTiles;
+pub use crate::binary_hash_scheme::BinaryHashScheme;
 pub use crate::binary_hash_tile::BinaryHashTile;
+pub use crate::cell_id::CellId;
+pub use crate::crs::Crs;
+#[cfg(feature = "polars")]
+pub use crate::dataframe::{
+    binary_hash_expr, cell_expr, cell_id_expr, dataframe_with_tile_id, lazyframe_with_tile_id,
+    tile_id_expr,
+};
+pub use crate::geometry_assignment::GeometryAssignment;
+#[cfg(feature = "parquet")]
+pub use crate::geoparquet::{GeoParquetWriter, GeometryColumn};
+#[cfg(feature = "h3")]
+pub use crate::h3_scheme::{H3Scheme, H3Tile};
+pub use crate::invalid_coordinate_policy::InvalidCoordinatePolicy;
+pub use crate::merged_tile::MergedTile;
+pub use crate::overflow_policy::OverflowPolicy;
+pub use crate::quadkey_scheme::{QuadkeyScheme, QuadkeyTile, XyzTile};
+pub use crate::s2_scheme::{S2CellId, S2Scheme, S2Tile};
+pub use crate::spatial_scheme::SpatialScheme;
+pub use crate::split_mode::SplitMode;
+pub use crate::tile_assignment::TileAssignment;
+pub use crate::tile_changes::TileChanges;
+pub use crate::tile_geojson::{tiles_to_geojson, write_tiles_geojson, write_tiles_geojson_file};
 pub use crate::tiler::Tiler;
+pub use crate::tiler_error::TilerError;
@@ -0,0 +1,733 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use geo::{Centroid, Geometry};
+use geo_data_tiler::{
+    write_tiles_geojson, BinaryHashScheme, BinaryHashTile, Crs, GeoParquetWriter,
+    GeometryAssignment, GeometryColumn, InvalidCoordinatePolicy, OverflowPolicy, SplitMode,
+    TileAssignment, Tiler,
+};
+use geojson::{Feature, FeatureCollection, GeoJson};
+
+const DEFAULT_BINARY_HASH_PRECISION: u8 = 24;
+const DEFAULT_REFINEMENT_PRECISION: u8 = 63;
+
+#[derive(Parser)]
+#[command(
+    name = "geo_data_tiler",
+    version,
+    about = "Split geodata into adaptive binary hash tiles"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the tile index
+    Index {
+        #[command(flatten)]
+        tiler_args: TilerArgs,
+        #[arg(long, value_enum, default_value_t = IndexFormat::Csv)]
+        output_format: IndexFormat,
+    },
+    /// Write one output file per tile
+    Partition {
+        #[command(flatten)]
+        tiler_args: TilerArgs,
+        #[arg(long)]
+        output_directory: PathBuf,
+        #[arg(long, default_value_t = 64)]
+        max_open_files: usize,
+    },
+}
+
+#[derive(Args)]
+struct TilerArgs {
+    input: PathBuf,
+    /// Input format, inferred from the file extension when omitted
+    #[arg(long, value_enum)]
+    format: Option<InputFormat>,
+    /// [default: 24, or 23 for --split-mode quadtree on the globe]
+    #[arg(long)]
+    binary_hash_precision: Option<u8>,
+    #[arg(long, default_value_t = 100000)]
+    max_allowed_features_in_binary_hash: u64,
+    #[arg(long, default_value = "lat")]
+    latitude_column: String,
+    #[arg(long, default_value = "lon")]
+    longitude_column: String,
+    /// WKB geometry column of a (Geo)Parquet input, used instead of the latitude and longitude columns
+    #[arg(long)]
+    geometry_column: Option<String>,
+    #[arg(long, value_enum, default_value_t = Assignment::Centroid)]
+    geometry_assignment: Assignment,
+    /// What to do with NaN, infinite or out of range coordinates
+    #[arg(long, value_enum, default_value_t = InvalidCoordinates::Reject)]
+    invalid_coordinates: InvalidCoordinates,
+    /// What to do with tiles that exceed --max-allowed-features-in-binary-hash at
+    /// --binary-hash-precision
+    #[arg(long, value_enum, default_value_t = Overflow::Allow)]
+    overflow: Overflow,
+    /// Finest precision that `--overflow refine` splits overflowing tiles to [default: 63, or 62
+    /// for --split-mode quadtree of a square --root-extent]
+    #[arg(long)]
+    refinement_precision: Option<u8>,
+    /// Count in coarse cells and split only the heavy ones, with --binary-hash-precision as the
+    /// finest precision
+    #[arg(long)]
+    adaptive_precision: bool,
+    /// Split the heaviest tile until there are this many tiles, instead of splitting every tile
+    /// that exceeds --max-allowed-features-in-binary-hash
+    #[arg(long)]
+    tile_count: Option<usize>,
+    /// Also emit empty tiles, so that the tiles cover the whole world
+    #[arg(long)]
+    include_empty_tiles: bool,
+    /// Bisect this extent instead of the whole globe, as MIN_LON,MIN_LAT,MAX_LON,MAX_LAT
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    root_extent: Option<Vec<f64>>,
+    /// EPSG code of the input coordinates and geometries, with x in --longitude-column and y in
+    /// --latitude-column
+    #[arg(long, default_value_t = 4326)]
+    crs: u32,
+    /// Tile x and y in the --crs instead of transforming coordinates to WGS84. --root-extent is
+    /// then in the --crs too
+    #[arg(long)]
+    planar: bool,
+    /// How heavy tiles are split. quadtree cuts both axes at once and only emits square tiles,
+    /// at odd precisions on the globe or even precisions of a square --root-extent, so
+    /// --binary-hash-precision and --refinement-precision must have that parity. Their defaults
+    /// are lowered by one when needed
+    #[arg(long, value_enum, default_value_t = Split::Bisect)]
+    split_mode: Split,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    Csv,
+    Geojson,
+    Parquet,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IndexFormat {
+    Csv,
+    Geojson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Assignment {
+    Centroid,
+    BoundingBox,
+    Intersection,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum InvalidCoordinates {
+    Reject,
+    Skip,
+    Clamp,
+}
+
+impl From<InvalidCoordinates> for InvalidCoordinatePolicy {
+    fn from(invalid_coordinates: InvalidCoordinates) -> Self {
+        match invalid_coordinates {
+            InvalidCoordinates::Reject => InvalidCoordinatePolicy::Reject,
+            InvalidCoordinates::Skip => InvalidCoordinatePolicy::Skip,
+            InvalidCoordinates::Clamp => InvalidCoordinatePolicy::Clamp,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Overflow {
+    Allow,
+    Reject,
+    Refine,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Split {
+    Bisect,
+    Quadtree,
+}
+
+impl From<Split> for SplitMode {
+    fn from(split: Split) -> Self {
+        match split {
+            Split::Bisect => SplitMode::Bisect,
+            Split::Quadtree => SplitMode::Quadtree,
+        }
+    }
+}
+
+impl From<Assignment> for GeometryAssignment {
+    fn from(assignment: Assignment) -> Self {
+        match assignment {
+            Assignment::Centroid => GeometryAssignment::Centroid,
+            Assignment::BoundingBox => GeometryAssignment::BoundingBox,
+            Assignment::Intersection => GeometryAssignment::Intersection,
+        }
+    }
+}
+
+impl TilerArgs {
+    fn input_format(&self) -> Result<InputFormat, Box<dyn Error>> {
+        if let Some(format) = self.format {
+            return Ok(format);
+        }
+        let extension = self
+            .input
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Ok(InputFormat::Csv),
+            Some("geojson") | Some("json") => Ok(InputFormat::Geojson),
+            Some("parquet") | Some("geoparquet") => Ok(InputFormat::Parquet),
+            _ => Err(format!(
+                "cannot infer the format of {}, pass --format",
+                self.input.display()
+            )
+            .into()),
+        }
+    }
+
+    fn crs(&self) -> Result<Crs, Box<dyn Error>> {
+        Ok(Crs::from_epsg(self.crs)?)
+    }
+
+    fn binary_hash_scheme(&self) -> Result<BinaryHashScheme, Box<dyn Error>> {
+        let binary_hash_scheme = if self.planar {
+            BinaryHashScheme::projected(self.crs()?)
+        } else {
+            BinaryHashScheme::default()
+        };
+        match self.root_extent.as_deref() {
+            None => Ok(binary_hash_scheme),
+            Some([min_lon, min_lat, max_lon, max_lat]) => Ok(BinaryHashScheme {
+                min_lon: *min_lon,
+                min_lat: *min_lat,
+                max_lon: *max_lon,
+                max_lat: *max_lat,
+                ..binary_hash_scheme
+            }),
+            Some(_) => Err("--root-extent must be MIN_LON,MIN_LAT,MAX_LON,MAX_LAT".into()),
+        }
+    }
+
+    fn overflow_policy(&self, square_precision_parity: Option<u8>) -> OverflowPolicy {
+        match self.overflow {
+            Overflow::Allow => OverflowPolicy::Allow,
+            Overflow::Reject => OverflowPolicy::Reject,
+            Overflow::Refine => OverflowPolicy::Refine {
+                max_precision: self.precision(
+                    self.refinement_precision,
+                    DEFAULT_REFINEMENT_PRECISION,
+                    square_precision_parity,
+                ),
+            },
+        }
+    }
+
+    /// `precision` if it is given, otherwise `default`, lowered by one if quadtree splits need
+    /// precisions of the other parity.
+    fn precision(
+        &self,
+        precision: Option<u8>,
+        default: u8,
+        square_precision_parity: Option<u8>,
+    ) -> u8 {
+        match (precision, square_precision_parity) {
+            (Some(precision), _) => precision,
+            (None, Some(parity)) if self.split_mode == Split::Quadtree && default % 2 != parity => {
+                default - 1
+            }
+            (None, _) => default,
+        }
+    }
+
+    fn geometry_column(&self) -> GeometryColumn {
+        match &self.geometry_column {
+            Some(geometry_column) => GeometryColumn::Wkb(geometry_column.clone()),
+            None => GeometryColumn::Coordinates {
+                latitude_column: self.latitude_column.clone(),
+                longitude_column: self.longitude_column.clone(),
+            },
+        }
+    }
+}
+
+fn main() {
+    if let Err(error) = run(Cli::parse(), io::stdout()) {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
+
+/// Runs `cli`, writing the tile index of `index` to `output`.
+fn run<W: Write>(cli: Cli, output: W) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Index {
+            tiler_args,
+            output_format,
+        } => {
+            let binary_hash_tiles = get_tiles(&tiler_args)?;
+            match output_format {
+                IndexFormat::Csv => write_tile_index(&binary_hash_tiles, output),
+                IndexFormat::Geojson => Ok(write_tiles_geojson(
+                    &tiler_args.binary_hash_scheme()?,
+                    &binary_hash_tiles,
+                    output,
+                )?),
+            }
+        }
+        Command::Partition {
+            tiler_args,
+            output_directory,
+            max_open_files,
+        } => {
+            let binary_hash_tiles = get_tiles(&tiler_args)?;
+            partition(
+                &tiler_args,
+                &binary_hash_tiles,
+                &output_directory,
+                max_open_files,
+            )
+        }
+    }
+}
+
+fn build_tiler(tiler_args: &TilerArgs) -> Result<Tiler, Box<dyn Error>> {
+    let mut tiler = Tiler::with_scheme(
+        tiler_args.binary_hash_scheme()?,
+        tiler_args
+            .binary_hash_precision
+            .unwrap_or(DEFAULT_BINARY_HASH_PRECISION),
+        tiler_args.max_allowed_features_in_binary_hash,
+    )?;
+    let square_precision_parity = tiler.square_precision_parity();
+    tiler.binary_hash_precision = tiler_args.precision(
+        tiler_args.binary_hash_precision,
+        DEFAULT_BINARY_HASH_PRECISION,
+        square_precision_parity,
+    );
+    tiler.invalid_coordinate_policy = tiler_args.invalid_coordinates.into();
+    tiler.overflow_policy = tiler_args.overflow_policy(square_precision_parity);
+    tiler.adaptive_precision = tiler_args.adaptive_precision;
+    tiler.include_empty_tiles = tiler_args.include_empty_tiles;
+    tiler.split_mode = tiler_args.split_mode.into();
+    tiler.input_crs = tiler_args.crs()?;
+
+    match tiler_args.input_format()? {
+        InputFormat::Csv => {
+            let mut csv_reader = csv::Reader::from_path(&tiler_args.input)?;
+            let (latitude_index, longitude_index) = coordinate_indices(
+                csv_reader.headers()?,
+                &tiler_args.latitude_column,
+                &tiler_args.longitude_column,
+            )?;
+            for record in csv_reader.records() {
+                let record = record?;
+                let Some((y, x)) = parse_coordinate(
+                    &record,
+                    latitude_index,
+                    longitude_index,
+                    tiler.invalid_coordinate_policy,
+                )?
+                else {
+                    tiler.skipped_coordinate_count += 1;
+                    continue;
+                };
+                tiler.add_projected_coordinate(x, y)?;
+            }
+        }
+        InputFormat::Geojson => {
+            for feature in read_geojson_features(&tiler_args.input)? {
+                if let Some(geometry) = feature_geometry(&feature)? {
+                    tiler.add_geometry(&geometry, tiler_args.geometry_assignment.into())?;
+                }
+            }
+        }
+        InputFormat::Parquet => {
+            tiler.add_parquet(
+                &tiler_args.input,
+                &tiler_args.geometry_column(),
+                tiler_args.geometry_assignment.into(),
+            )?;
+        }
+    }
+
+    if tiler.skipped_coordinate_count > 0 {
+        eprintln!(
+            "skipped {} invalid coordinates",
+            tiler.skipped_coordinate_count
+        );
+    }
+    Ok(tiler)
+}
+
+fn get_tiles(tiler_args: &TilerArgs) -> Result<HashMap<String, BinaryHashTile>, Box<dyn Error>> {
+    let tiler = build_tiler(tiler_args)?;
+    if tiler_args.adaptive_precision {
+        let mut cell_counts: BTreeMap<u8, usize> = BTreeMap::new();
+        for precision in tiler.get_effective_precisions().into_values() {
+            *cell_counts.entry(precision).or_insert(0) += 1;
+        }
+        for (precision, cell_count) in cell_counts {
+            eprintln!("effective precision {precision}: {cell_count} cells");
+        }
+    }
+
+    if let Some(tile_count) = tiler_args.tile_count {
+        let balanced_tiles = tiler.get_tiles_with_count(tile_count)?;
+        eprintln!(
+            "{} tiles, imbalance ratio {}",
+            balanced_tiles.binary_hash_tiles.len(),
+            balanced_tiles.imbalance_ratio
+        );
+        return Ok(balanced_tiles.binary_hash_tiles);
+    }
+
+    let binary_hash_tiles = tiler.get_tiles()?;
+    let overflowing_tile_count = binary_hash_tiles
+        .values()
+        .filter(|binary_hash_tile| {
+            binary_hash_tile.weight > tiler_args.max_allowed_features_in_binary_hash as f64
+        })
+        .count();
+    if overflowing_tile_count > 0 {
+        eprintln!(
+            "{overflowing_tile_count} tiles exceed --max-allowed-features-in-binary-hash and cannot be split further"
+        );
+    }
+    Ok(binary_hash_tiles)
+}
+
+fn write_tile_index<W: Write>(
+    binary_hash_tiles: &HashMap<String, BinaryHashTile>,
+    output: W,
+) -> Result<(), Box<dyn Error>> {
+    let mut binary_hashes: Vec<&String> = binary_hash_tiles.keys().collect();
+    binary_hashes.sort();
+
+    let mut csv_writer = csv::Writer::from_writer(output);
+    csv_writer.write_record([
+        "binary_hash",
+        "node_count",
+        "weight",
+        "min_lon",
+        "min_lat",
+        "max_lon",
+        "max_lat",
+    ])?;
+    for binary_hash in binary_hashes {
+        let binary_hash_tile = &binary_hash_tiles[binary_hash];
+        csv_writer.write_record([
+            binary_hash.clone(),
+            binary_hash_tile.node_count.to_string(),
+            binary_hash_tile.weight.to_string(),
+            binary_hash_tile.min_lon.to_string(),
+            binary_hash_tile.min_lat.to_string(),
+            binary_hash_tile.max_lon.to_string(),
+            binary_hash_tile.max_lat.to_string(),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+fn partition(
+    tiler_args: &TilerArgs,
+    binary_hash_tiles: &HashMap<String, BinaryHashTile>,
+    output_directory: &Path,
+    max_open_files: usize,
+) -> Result<(), Box<dyn Error>> {
+    if max_open_files == 0 {
+        return Err("--max-open-files must be at least 1".into());
+    }
+    fs::create_dir_all(output_directory)?;
+    let binary_hash_scheme = tiler_args.binary_hash_scheme()?;
+    let tile_assignment = TileAssignment::with_scheme(binary_hash_scheme, binary_hash_tiles);
+    let crs = tiler_args.crs()?;
+
+    match tiler_args.input_format()? {
+        InputFormat::Csv => {
+            let mut binary_hashes: Vec<&String> = binary_hash_tiles.keys().collect();
+            binary_hashes.sort();
+
+            // Read the input once per group of tiles to keep the number of open files bounded.
+            for binary_hash_group in binary_hashes.chunks(max_open_files) {
+                let binary_hash_group: HashSet<&str> = binary_hash_group
+                    .iter()
+                    .map(|binary_hash| binary_hash.as_str())
+                    .collect();
+                let mut csv_reader = csv::Reader::from_path(&tiler_args.input)?;
+                let headers = csv_reader.headers()?.clone();
+                let (latitude_index, longitude_index) = coordinate_indices(
+                    &headers,
+                    &tiler_args.latitude_column,
+                    &tiler_args.longitude_column,
+                )?;
+
+                let mut csv_writers: HashMap<&str, csv::Writer<File>> = HashMap::new();
+                for record in csv_reader.records() {
+                    let record = record?;
+                    let Some((y, x)) = parse_coordinate(
+                        &record,
+                        latitude_index,
+                        longitude_index,
+                        tiler_args.invalid_coordinates.into(),
+                    )?
+                    else {
+                        continue;
+                    };
+                    let Some(binary_hash) = tile_assignment
+                        .tile_for_projected(&crs, x, y)
+                        .and_then(|binary_hash| binary_hash_group.get(binary_hash))
+                    else {
+                        continue;
+                    };
+                    if !csv_writers.contains_key(binary_hash) {
+                        let mut csv_writer = csv::Writer::from_path(
+                            output_directory.join(format!("{binary_hash}.csv")),
+                        )?;
+                        csv_writer.write_record(&headers)?;
+                        csv_writers.insert(binary_hash, csv_writer);
+                    }
+                    csv_writers
+                        .get_mut(binary_hash)
+                        .unwrap()
+                        .write_record(&record)?;
+                }
+                for csv_writer in csv_writers.values_mut() {
+                    csv_writer.flush()?;
+                }
+            }
+        }
+        InputFormat::Geojson => {
+            let mut tile_features: HashMap<&str, Vec<Feature>> = HashMap::new();
+            for feature in read_geojson_features(&tiler_args.input)? {
+                let Some(centroid) =
+                    feature_geometry(&feature)?.and_then(|geometry| geometry.centroid())
+                else {
+                    continue;
+                };
+                if let Some(binary_hash) =
+                    tile_assignment.tile_for_projected(&crs, centroid.x(), centroid.y())
+                {
+                    tile_features.entry(binary_hash).or_default().push(feature);
+                }
+            }
+            for (binary_hash, features) in tile_features {
+                let feature_collection = FeatureCollection {
+                    bbox: None,
+                    features,
+                    foreign_members: None,
+                };
+                fs::write(
+                    output_directory.join(format!("{binary_hash}.geojson")),
+                    GeoJson::from(feature_collection).to_string(),
+                )?;
+            }
+        }
+        InputFormat::Parquet => {
+            let mut geoparquet_writer = GeoParquetWriter::new(tiler_args.geometry_column());
+            geoparquet_writer.input_crs = crs;
+            geoparquet_writer.max_open_files = max_open_files;
+            geoparquet_writer.write(&tile_assignment, &tiler_args.input, output_directory)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn coordinate_indices(
+    headers: &csv::StringRecord,
+    latitude_column: &str,
+    longitude_column: &str,
+) -> Result<(usize, usize), Box<dyn Error>> {
+    let column_index = |column: &str| {
+        headers
+            .iter()
+            .position(|header| header == column)
+            .ok_or_else(|| format!("missing column {column}"))
+    };
+    Ok((
+        column_index(latitude_column)?,
+        column_index(longitude_column)?,
+    ))
+}
+
+/// Parses the coordinate of `record`. Returns `None` for an empty or non-numeric coordinate,
+/// unless `invalid_coordinate_policy` rejects it.
+fn parse_coordinate(
+    record: &csv::StringRecord,
+    latitude_index: usize,
+    longitude_index: usize,
+    invalid_coordinate_policy: InvalidCoordinatePolicy,
+) -> Result<Option<(f64, f64)>, Box<dyn Error>> {
+    let parse_value = |index: usize| -> Result<Option<f64>, Box<dyn Error>> {
+        let value = record.get(index).unwrap_or_default().trim();
+        match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) if invalid_coordinate_policy != InvalidCoordinatePolicy::Reject => Ok(None),
+            Err(_) => {
+                let line = record
+                    .position()
+                    .map(|position| position.line())
+                    .unwrap_or_default();
+                Err(format!("invalid coordinate {value:?} on line {line}").into())
+            }
+        }
+    };
+    Ok(parse_value(latitude_index)?.zip(parse_value(longitude_index)?))
+}
+
+fn read_geojson_features(input: &Path) -> Result<Vec<Feature>, Box<dyn Error>> {
+    let geojson: GeoJson = fs::read_to_string(input)?.parse()?;
+    Ok(match geojson {
+        GeoJson::FeatureCollection(feature_collection) => feature_collection.features,
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::Geometry(geometry) => vec![Feature::from(geometry)],
+    })
+}
+
+fn feature_geometry(feature: &Feature) -> Result<Option<Geometry<f64>>, Box<dyn Error>> {
+    let Some(geometry) = &feature.geometry else {
+        return Ok(None);
+    };
+    Ok(Some(Geometry::<f64>::try_from(geometry.value.clone())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> String {
+        let mut output = Vec::new();
+        run(
+            Cli::try_parse_from([&["geo_data_tiler"], args].concat()).unwrap(),
+            &mut output,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn indexes_and_partitions_a_csv() {
+        let directory = tempfile::tempdir().unwrap();
+        let input = directory.path().join("points.csv");
+        let mut csv = String::from("id,lat,lon\n");
+        for index in 0..40 {
+            let index = index as f64;
+            csv.push_str(&format!(
+                "{index},{},{}\n",
+                48.0 + index * 0.1,
+                -20.0 + index * 1.3
+            ));
+        }
+        csv.push_str("40,-33.9,151.2\n41,,151.2\n");
+        fs::write(&input, csv).unwrap();
+        let input = input.to_str().unwrap();
+        let tiler_args = [
+            input,
+            "--invalid-coordinates",
+            "skip",
+            "--binary-hash-precision",
+            "12",
+            "--max-allowed-features-in-binary-hash",
+            "8",
+        ];
+
+        let index = run_args(&[&["index"], &tiler_args[..]].concat());
+        let mut node_counts = HashMap::new();
+        for record in csv::Reader::from_reader(index.as_bytes()).records() {
+            let record = record.unwrap();
+            node_counts.insert(record[0].to_string(), record[1].parse::<usize>().unwrap());
+        }
+        assert!(node_counts.len() > 4);
+        assert_eq!(node_counts.values().sum::<usize>(), 41);
+        assert!(node_counts.values().all(|node_count| *node_count <= 8));
+
+        let output_directory = directory.path().join("tiles");
+        run_args(
+            &[
+                &["partition"],
+                &tiler_args[..],
+                &[
+                    "--output-directory",
+                    output_directory.to_str().unwrap(),
+                    "--max-open-files",
+                    "2",
+                ],
+            ]
+            .concat(),
+        );
+        let output_files: Vec<PathBuf> = fs::read_dir(&output_directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(output_files.len(), node_counts.len());
+        for output_file in output_files {
+            let binary_hash = output_file.file_stem().unwrap().to_str().unwrap();
+            let row_count = csv::Reader::from_path(&output_file)
+                .unwrap()
+                .records()
+                .count();
+            assert_eq!(row_count, node_counts[binary_hash]);
+        }
+    }
+
+    #[test]
+    fn quadtree_splits_default_to_square_precisions() {
+        let directory = tempfile::tempdir().unwrap();
+        let input = directory.path().join("points.csv");
+        fs::write(&input, "lat,lon\n1.0,1.0\n1.0,2.0\n8.0,8.0\n").unwrap();
+        let input = input.to_str().unwrap();
+        let build = |args: &[&str]| {
+            let Command::Index { tiler_args, .. } =
+                Cli::try_parse_from([&["geo_data_tiler", "index", input], args].concat())
+                    .unwrap()
+                    .command
+            else {
+                unreachable!()
+            };
+            build_tiler(&tiler_args)
+        };
+
+        assert_eq!(build(&[]).unwrap().binary_hash_precision, 24);
+        let tiler = build(&["--split-mode", "quadtree"]).unwrap();
+        assert_eq!(tiler.binary_hash_precision, 23);
+        assert!(tiler.get_tiles().is_ok());
+
+        let tiler = build(&[
+            "--split-mode",
+            "quadtree",
+            "--root-extent",
+            "0,0,10,10",
+            "--overflow",
+            "refine",
+            "--max-allowed-features-in-binary-hash",
+            "1",
+        ])
+        .unwrap();
+        assert_eq!(tiler.binary_hash_precision, 24);
+        assert_eq!(
+            tiler.overflow_policy,
+            OverflowPolicy::Refine { max_precision: 62 }
+        );
+        assert!(tiler.get_tiles().is_ok());
+
+        // Explicit precisions are kept, and fail with a hint if they have the wrong parity.
+        let tiler = build(&["--split-mode", "quadtree", "--binary-hash-precision", "24"]).unwrap();
+        let error = tiler.get_tiles().unwrap_err().to_string();
+        assert!(error.contains("odd precision such as 23 or 25"), "{error}");
+    }
+}
@@ -0,0 +1,17 @@
+/// A tile from `Tiler::get_merged_tiles`: one tile of `get_tiles`, or several adjacent ones merged
+/// because they had fewer than `min_features_in_tile` features.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedTile {
+    /// Tile ids of the cells making up this tile, sorted.
+    pub binary_hashes: Vec<String>,
+    pub node_count: i64,
+    pub weight: f64,
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
@@ -0,0 +1,15 @@
+/// What `Tiler` does with a cell at `binary_hash_precision` that is heavier than
+/// `max_allowed_features_in_binary_hash` and so cannot be split any further.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OverflowPolicy {
+    /// Emit the cell as an oversized tile. `Tiler::get_overflowing_tiles` lists such tiles.
+    #[default]
+    Allow,
+    /// Fail tiling with `TilerError::TileOverflow`.
+    Reject,
+    /// Keep the raw coordinates of every cell, and split overflowing cells further by
+    /// re-encoding only their coordinates, down to `max_precision`. Must be set before adding
+    /// coordinates. A non-point geometry is re-encoded at the centre of the part of its bounding
+    /// box within each of its cells.
+    Refine { max_precision: u8 },
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use geohashrust::BoundingBox;
+
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::spatial_scheme::SpatialScheme;
+
+/// Latitude limit of the square EPSG:3857 world.
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Web Mercator (EPSG:3857) slippy map tiles, identified by quadkey strings.
+///
+/// The precision of a cell is its zoom level, so every tile produced by a `Tiler` using this scheme is
+/// a standard XYZ web map tile. Each zoom level spans two `CellId` levels, one per quadkey bit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QuadkeyScheme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XyzTile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuadkeyTile {
+    pub xyz_tile: XyzTile,
+    pub quadkey: String,
+    pub node_count: i64,
+    pub weight: f64,
+}
+
+impl QuadkeyTile {
+    pub(crate) fn from_cell_tiles(
+        cell_tiles: HashMap<CellId, BinaryHashTile>,
+    ) -> HashMap<XyzTile, QuadkeyTile> {
+        cell_tiles
+            .into_iter()
+            .map(|(cell, binary_hash_tile)| {
+                let xyz_tile = XyzTile::from_cell_id(cell);
+                let quadkey_tile = QuadkeyTile {
+                    xyz_tile,
+                    quadkey: xyz_tile.quadkey(),
+                    node_count: binary_hash_tile.node_count,
+                    weight: binary_hash_tile.weight,
+                };
+                (xyz_tile, quadkey_tile)
+            })
+            .collect()
+    }
+}
+
+impl XyzTile {
+    pub fn from_quadkey(quadkey: &str) -> Self {
+        let mut xyz_tile = XyzTile { z: 0, x: 0, y: 0 };
+        for digit in quadkey.bytes() {
+            let digit = u32::from(digit - b'0');
+            xyz_tile.z += 1;
+            xyz_tile.x = (xyz_tile.x << 1) | (digit & 1);
+            xyz_tile.y = (xyz_tile.y << 1) | (digit >> 1);
+        }
+        xyz_tile
+    }
+
+    pub fn quadkey(&self) -> String {
+        (1..=self.z)
+            .rev()
+            .map(|i| {
+                let digit = ((self.x >> (i - 1)) & 1) + 2 * ((self.y >> (i - 1)) & 1);
+                char::from(b'0' + digit as u8)
+            })
+            .collect()
+    }
+
+    /// Interleaves the y and x bits of each zoom level, matching the quadkey digits.
+    pub fn cell_id(&self) -> CellId {
+        let bits = (0..self.z).rev().fold(0u64, |bits, i| {
+            (bits << 2) | (u64::from((self.y >> i) & 1) << 1) | u64::from((self.x >> i) & 1)
+        });
+        CellId::from_bits(bits, 2 * self.z)
+    }
+
+    pub fn from_cell_id(cell_id: CellId) -> Self {
+        let z = cell_id.level() / 2;
+        let bits = cell_id.bits();
+        let mut xyz_tile = XyzTile { z, x: 0, y: 0 };
+        for i in (0..z).rev() {
+            let digit = (bits >> (2 * i)) & 3;
+            xyz_tile.x = (xyz_tile.x << 1) | (digit & 1) as u32;
+            xyz_tile.y = (xyz_tile.y << 1) | (digit >> 1) as u32;
+        }
+        xyz_tile
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        let tile_count = f64::from(1u32 << self.z);
+        let longitude = |x: u32| f64::from(x) / tile_count * 360.0 - 180.0;
+        let latitude = |y: u32| {
+            (PI * (1.0 - 2.0 * f64::from(y) / tile_count))
+                .sinh()
+                .atan()
+                .to_degrees()
+        };
+        BoundingBox {
+            min_lon: longitude(self.x),
+            max_lon: longitude(self.x + 1),
+            min_lat: latitude(self.y + 1),
+            max_lat: latitude(self.y),
+        }
+    }
+}
+
+impl SpatialScheme for QuadkeyScheme {
+    fn max_precision(&self) -> u8 {
+        31
+    }
+
+    fn encode(&self, latitude: f64, longitude: f64, precision: u8) -> CellId {
+        let tile_count = f64::from(1u32 << precision);
+        let latitude = latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+        let x = ((longitude + 180.0) / 360.0 * tile_count).floor();
+        let y = ((1.0 - latitude.tan().asinh() / PI) / 2.0 * tile_count).floor();
+        let max_tile = tile_count - 1.0;
+
+        XyzTile {
+            z: precision,
+            x: x.clamp(0.0, max_tile) as u32,
+            y: y.clamp(0.0, max_tile) as u32,
+        }
+        .cell_id()
+    }
+
+    fn precision(&self, cell: CellId) -> u8 {
+        cell.level() / 2
+    }
+
+    fn parent(&self, cell: CellId, precision: u8) -> CellId {
+        cell.parent(2 * precision)
+    }
+
+    fn children(&self, cell: CellId) -> Vec<CellId> {
+        cell.descendants(cell.level() + 2).collect()
+    }
+
+    fn root_cells(&self) -> Vec<CellId> {
+        self.children(CellId::ROOT)
+    }
+
+    fn bounds(&self, cell: CellId) -> BoundingBox {
+        XyzTile::from_cell_id(cell).bounds()
+    }
+
+    fn token(&self, cell: CellId) -> String {
+        XyzTile::from_cell_id(cell).quadkey()
+    }
+
+    fn parse_token(&self, token: &str) -> CellId {
+        XyzTile::from_quadkey(token).cell_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::Tiler;
+
+    #[test]
+    fn tiles_match_xyz_tiles() {
+        // Vienna, tile 12/2234/1420 in the OpenStreetMap tile scheme.
+        let quadkey = QuadkeyScheme.token(QuadkeyScheme.encode(48.2082, 16.3738, 12));
+        assert_eq!(
+            XyzTile::from_quadkey(&quadkey),
+            XyzTile {
+                z: 12,
+                x: 2234,
+                y: 1420
+            }
+        );
+        assert_eq!(XyzTile::from_quadkey(&quadkey).quadkey(), quadkey);
+        assert_eq!(
+            XyzTile::from_cell_id(QuadkeyScheme.parse_token(&quadkey)),
+            XyzTile::from_quadkey(&quadkey)
+        );
+        assert_eq!(
+            QuadkeyScheme
+                .root_cells()
+                .into_iter()
+                .map(|cell| QuadkeyScheme.token(cell))
+                .collect::<Vec<_>>(),
+            vec!["0", "1", "2", "3"]
+        );
+
+        let mut tiler = Tiler::quadkey(16, 2).unwrap();
+        tiler.add_coordinate(48.2082, 16.3738).unwrap();
+        tiler.add_coordinate(48.2085, 16.3740).unwrap();
+        tiler.add_coordinate(-33.8688, 151.2093).unwrap();
+
+        let tiles = tiler.get_tiles().unwrap();
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles["1"].node_count, 2);
+        assert_eq!(tiles["3"].node_count, 1);
+        assert!((tiles["1"].max_lat - MAX_LATITUDE).abs() < 1e-9);
+        assert_eq!(tiles["3"].min_lon, 0.0);
+
+        let xyz_tiles = tiler.get_xyz_tiles().unwrap();
+        assert_eq!(xyz_tiles.len(), 2);
+        let xyz_tile = XyzTile { z: 1, x: 1, y: 0 };
+        assert_eq!(xyz_tiles[&xyz_tile].xyz_tile, xyz_tile);
+        assert_eq!(xyz_tiles[&xyz_tile].quadkey, "1");
+        assert_eq!(xyz_tiles[&xyz_tile].node_count, 2);
+        assert_eq!(xyz_tiles[&XyzTile { z: 1, x: 1, y: 1 }].node_count, 1);
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use geo::{LineString, Polygon, Rect};
+use geohashrust::BoundingBox;
+
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::spatial_scheme::{ring_bounds, unwrap_antimeridian, SpatialScheme};
+
+const MAX_LEVEL: u8 = 30;
+const MAX_SIZE: u32 = 1 << MAX_LEVEL;
+const POS_BITS: u32 = 2 * MAX_LEVEL as u32 + 1;
+
+const SWAP_MASK: u8 = 1;
+const INVERT_MASK: u8 = 2;
+const IJ_TO_POS: [[u8; 4]; 4] = [[0, 1, 3, 2], [0, 3, 1, 2], [2, 3, 1, 0], [2, 1, 3, 0]];
+const POS_TO_IJ: [[u8; 4]; 4] = [[0, 1, 3, 2], [0, 2, 3, 1], [3, 2, 0, 1], [3, 1, 0, 2]];
+const POS_TO_ORIENTATION: [u8; 4] = [SWAP_MASK, 0, 0, INVERT_MASK | SWAP_MASK];
+
+/// Points sampled along each cell edge for `bounds` and `polygon`, since S2 edges are geodesics.
+const EDGE_SAMPLES: usize = 8;
+
+/// Google's S2 cells, identified by their tokens.
+///
+/// The precision of a cell is its S2 level. Level 0 holds the six cube faces, and cells are close
+/// to equal-area everywhere, including near the poles.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct S2Scheme;
+
+/// A 64-bit S2 cell id: three face bits, two Hilbert curve bits per level and a trailing 1 bit.
+///
+/// This is the same packing as `CellId`, so the two convert into each other unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct S2CellId(pub u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S2Tile {
+    pub cell_id: S2CellId,
+    pub token: String,
+    /// The four cell vertices in counter-clockwise order.
+    pub vertices: Polygon<f64>,
+    pub node_count: i64,
+    pub weight: f64,
+}
+
+impl S2Tile {
+    pub(crate) fn from_cell_tiles(
+        cell_tiles: HashMap<CellId, BinaryHashTile>,
+    ) -> HashMap<S2CellId, S2Tile> {
+        cell_tiles
+            .into_iter()
+            .map(|(cell, binary_hash_tile)| {
+                let cell_id = S2CellId::from(cell);
+                let s2_tile = S2Tile {
+                    cell_id,
+                    vertices: cell_id.vertices(),
+                    token: cell_id.token(),
+                    node_count: binary_hash_tile.node_count,
+                    weight: binary_hash_tile.weight,
+                };
+                (cell_id, s2_tile)
+            })
+            .collect()
+    }
+}
+
+impl From<CellId> for S2CellId {
+    fn from(cell: CellId) -> Self {
+        S2CellId(cell.0)
+    }
+}
+
+impl From<S2CellId> for CellId {
+    fn from(cell_id: S2CellId) -> Self {
+        CellId(cell_id.0)
+    }
+}
+
+impl S2CellId {
+    pub fn from_lat_lng(latitude: f64, longitude: f64, level: u8) -> Self {
+        let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+        let point = [
+            latitude.cos() * longitude.cos(),
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+        ];
+        let (face, u, v) = xyz_to_face_uv(point);
+        let i = st_to_ij(uv_to_st(u));
+        let j = st_to_ij(uv_to_st(v));
+        S2CellId::from_face_ij(face, i, j).parent(level)
+    }
+
+    fn from_face_ij(face: u8, i: u32, j: u32) -> Self {
+        let mut id = u64::from(face) << POS_BITS;
+        let mut orientation = face & SWAP_MASK;
+        for k in (0..MAX_LEVEL as u32).rev() {
+            let ij = (((i >> k) & 1) << 1 | ((j >> k) & 1)) as usize;
+            let pos = IJ_TO_POS[orientation as usize][ij];
+            id |= u64::from(pos) << (2 * k + 1);
+            orientation ^= POS_TO_ORIENTATION[pos as usize];
+        }
+        S2CellId(id | 1)
+    }
+
+    pub fn from_token(token: &str) -> Self {
+        if token == "X" {
+            return S2CellId(0);
+        }
+        let id = u64::from_str_radix(token, 16)
+            .unwrap_or_else(|_| panic!("invalid S2 cell token {token}"));
+        S2CellId(id << (4 * (16 - token.len())))
+    }
+
+    /// The hexadecimal cell id without trailing zeros.
+    pub fn token(&self) -> String {
+        if self.0 == 0 {
+            return String::from("X");
+        }
+        let token = format!("{:016x}", self.0);
+        token.trim_end_matches('0').to_string()
+    }
+
+    pub fn face(&self) -> u8 {
+        (self.0 >> POS_BITS) as u8
+    }
+
+    pub fn level(&self) -> u8 {
+        MAX_LEVEL - (self.0.trailing_zeros() / 2) as u8
+    }
+
+    fn lowest_bit(level: u8) -> u64 {
+        1 << (2 * (MAX_LEVEL - level))
+    }
+
+    pub fn parent(&self, level: u8) -> Self {
+        let lowest_bit = S2CellId::lowest_bit(level);
+        S2CellId((self.0 & lowest_bit.wrapping_neg()) | lowest_bit)
+    }
+
+    /// The four children in Hilbert curve order, or none for leaf cells.
+    pub fn children(&self) -> Vec<Self> {
+        let level = self.level();
+        if level == MAX_LEVEL {
+            return Vec::new();
+        }
+        let child_lowest_bit = S2CellId::lowest_bit(level + 1);
+        let first_child = self.0 - S2CellId::lowest_bit(level) + child_lowest_bit;
+        (0..4)
+            .map(|k| S2CellId(first_child + k * 2 * child_lowest_bit))
+            .collect()
+    }
+
+    /// The (u, v) rectangle of the cell on its cube face.
+    fn uv_bounds(&self) -> [f64; 4] {
+        let face = self.face();
+        let level = self.level();
+        let mut orientation = face & SWAP_MASK;
+        let (mut i, mut j) = (0u32, 0u32);
+        for cell_level in 1..=level {
+            let k = u32::from(MAX_LEVEL - cell_level);
+            let pos = ((self.0 >> (2 * k + 1)) & 3) as usize;
+            let ij = POS_TO_IJ[orientation as usize][pos];
+            i |= u32::from(ij >> 1) << k;
+            j |= u32::from(ij & 1) << k;
+            orientation ^= POS_TO_ORIENTATION[pos];
+        }
+
+        let size = 1u64 << (MAX_LEVEL - level);
+        let uv = |ij: u64| st_to_uv(ij as f64 / f64::from(MAX_SIZE));
+        [
+            uv(u64::from(i)),
+            uv(u64::from(i) + size),
+            uv(u64::from(j)),
+            uv(u64::from(j) + size),
+        ]
+    }
+
+    /// Returns the cell boundary as (longitude, latitude) pairs, starting at each vertex and
+    /// adding `samples_per_edge - 1` points along the edge to the next vertex.
+    fn boundary(&self, samples_per_edge: usize) -> Vec<(f64, f64)> {
+        let face = self.face();
+        let [min_u, max_u, min_v, max_v] = self.uv_bounds();
+        let vertices = [
+            (min_u, min_v),
+            (max_u, min_v),
+            (max_u, max_v),
+            (min_u, max_v),
+        ];
+
+        let mut coordinates = Vec::with_capacity(4 * samples_per_edge);
+        for (k, (u, v)) in vertices.iter().enumerate() {
+            let (next_u, next_v) = vertices[(k + 1) % 4];
+            for sample in 0..samples_per_edge {
+                let fraction = sample as f64 / samples_per_edge as f64;
+                coordinates.push(face_uv_to_lon_lat(
+                    face,
+                    u + (next_u - u) * fraction,
+                    v + (next_v - v) * fraction,
+                ));
+            }
+        }
+
+        unwrap_antimeridian(&mut coordinates);
+        coordinates
+    }
+
+    pub fn vertices(&self) -> Polygon<f64> {
+        Polygon::new(LineString::from(self.boundary(1)), Vec::new())
+    }
+
+    fn contains_pole(&self, latitude: f64) -> bool {
+        S2CellId::from_lat_lng(latitude, 0.0, self.level()) == *self
+    }
+}
+
+fn xyz_to_face_uv([x, y, z]: [f64; 3]) -> (u8, f64, f64) {
+    let face = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x < 0.0 {
+            3
+        } else {
+            0
+        }
+    } else if y.abs() >= z.abs() {
+        if y < 0.0 {
+            4
+        } else {
+            1
+        }
+    } else if z < 0.0 {
+        5
+    } else {
+        2
+    };
+    let (u, v) = match face {
+        0 => (y / x, z / x),
+        1 => (-x / y, z / y),
+        2 => (-x / z, -y / z),
+        3 => (z / x, y / x),
+        4 => (z / y, -x / y),
+        _ => (-y / z, -x / z),
+    };
+    (face, u, v)
+}
+
+fn face_uv_to_lon_lat(face: u8, u: f64, v: f64) -> (f64, f64) {
+    let [x, y, z] = match face {
+        0 => [1.0, u, v],
+        1 => [-u, 1.0, v],
+        2 => [-u, -v, 1.0],
+        3 => [-1.0, -v, -u],
+        4 => [v, -1.0, -u],
+        _ => [v, u, -1.0],
+    };
+    (
+        y.atan2(x).to_degrees(),
+        z.atan2((x * x + y * y).sqrt()).to_degrees(),
+    )
+}
+
+/// S2's quadratic projection, which keeps cells at the same level close to equal-area.
+fn uv_to_st(u: f64) -> f64 {
+    if u >= 0.0 {
+        0.5 * (1.0 + 3.0 * u).sqrt()
+    } else {
+        1.0 - 0.5 * (1.0 - 3.0 * u).sqrt()
+    }
+}
+
+fn st_to_uv(s: f64) -> f64 {
+    if s >= 0.5 {
+        (4.0 * s * s - 1.0) / 3.0
+    } else {
+        (1.0 - 4.0 * (1.0 - s) * (1.0 - s)) / 3.0
+    }
+}
+
+fn st_to_ij(s: f64) -> u32 {
+    (f64::from(MAX_SIZE) * s)
+        .floor()
+        .clamp(0.0, f64::from(MAX_SIZE - 1)) as u32
+}
+
+impl SpatialScheme for S2Scheme {
+    fn max_precision(&self) -> u8 {
+        MAX_LEVEL
+    }
+
+    fn min_precision(&self) -> u8 {
+        0
+    }
+
+    fn encode(&self, latitude: f64, longitude: f64, precision: u8) -> CellId {
+        S2CellId::from_lat_lng(latitude, longitude, precision).into()
+    }
+
+    fn precision(&self, cell: CellId) -> u8 {
+        S2CellId::from(cell).level()
+    }
+
+    fn parent(&self, cell: CellId, precision: u8) -> CellId {
+        S2CellId::from(cell).parent(precision).into()
+    }
+
+    fn children(&self, cell: CellId) -> Vec<CellId> {
+        S2CellId::from(cell)
+            .children()
+            .into_iter()
+            .map(CellId::from)
+            .collect()
+    }
+
+    fn root_cells(&self) -> Vec<CellId> {
+        (0..6u64)
+            .map(|face| CellId((face << POS_BITS) | (1 << (POS_BITS - 1))))
+            .collect()
+    }
+
+    fn bounds(&self, cell: CellId) -> BoundingBox {
+        let cell_id = S2CellId::from(cell);
+        let mut bounds = ring_bounds(cell_id.boundary(EDGE_SAMPLES));
+
+        // A cell around a pole spans every longitude, which its boundary alone does not show.
+        if cell_id.contains_pole(90.0) || cell_id.contains_pole(-90.0) {
+            bounds.min_lon = -180.0;
+            bounds.max_lon = 180.0;
+            if cell_id.contains_pole(90.0) {
+                bounds.max_lat = 90.0;
+            }
+            if cell_id.contains_pole(-90.0) {
+                bounds.min_lat = -90.0;
+            }
+        }
+        bounds
+    }
+
+    fn polygon(&self, cell: CellId) -> Polygon<f64> {
+        let cell_id = S2CellId::from(cell);
+        if cell_id.contains_pole(90.0) || cell_id.contains_pole(-90.0) {
+            let bounds = self.bounds(cell);
+            return Rect::new(
+                (bounds.min_lon, bounds.min_lat),
+                (bounds.max_lon, bounds.max_lat),
+            )
+            .to_polygon();
+        }
+        Polygon::new(LineString::from(cell_id.boundary(EDGE_SAMPLES)), Vec::new())
+    }
+
+    fn token(&self, cell: CellId) -> String {
+        S2CellId::from(cell).token()
+    }
+
+    fn parse_token(&self, token: &str) -> CellId {
+        S2CellId::from_token(token).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::Tiler;
+
+    #[test]
+    fn cells_match_s2_cell_ids() {
+        let tokens = |cells: Vec<CellId>| -> Vec<String> {
+            cells.into_iter().map(|cell| S2Scheme.token(cell)).collect()
+        };
+        assert_eq!(
+            S2Scheme.token(S2Scheme.encode(0.0, 0.0, 30)),
+            "1000000000000001"
+        );
+        assert_eq!(
+            tokens(S2Scheme.root_cells()),
+            vec!["1", "3", "5", "7", "9", "b"]
+        );
+        assert_eq!(
+            tokens(S2Scheme.children(S2Scheme.parse_token("1"))),
+            vec!["04", "0c", "14", "1c"]
+        );
+        assert_eq!(
+            S2Scheme.token(S2Scheme.encode(40.7128, -74.0060, 8)),
+            "89c25"
+        );
+
+        let cell = S2Scheme.encode(78.2232, 15.6267, 12);
+        assert_eq!(S2Scheme.precision(cell), 12);
+        assert_eq!(S2Scheme.parse_token(&S2Scheme.token(cell)), cell);
+        assert!(S2Scheme.children(S2Scheme.parent(cell, 11)).contains(&cell));
+        assert!(S2Scheme.parent(cell, 11).contains(cell));
+        let bounds = S2Scheme.bounds(cell);
+        assert!(bounds.min_lat <= 78.2232 && 78.2232 <= bounds.max_lat);
+        assert!(bounds.min_lon <= 15.6267 && 15.6267 <= bounds.max_lon);
+
+        let north_pole_cell = S2Scheme.encode(90.0, 0.0, 3);
+        assert_eq!(S2Scheme.bounds(north_pole_cell).max_lat, 90.0);
+    }
+
+    #[test]
+    fn tiles_are_s2_cells() {
+        let mut tiler = Tiler::s2(20, 2).unwrap();
+        tiler.add_coordinate(78.2232, 15.6267).unwrap();
+        tiler.add_coordinate(78.2240, 15.6300).unwrap();
+        tiler.add_coordinate(78.9000, 11.9000).unwrap();
+        tiler.add_coordinate(-33.8688, 151.2093).unwrap();
+
+        let s2_tiles = tiler.get_s2_tiles().unwrap();
+        assert_eq!(
+            s2_tiles
+                .values()
+                .map(|s2_tile| s2_tile.node_count)
+                .sum::<i64>(),
+            4
+        );
+        assert!(s2_tiles.values().all(|s2_tile| s2_tile.node_count <= 2));
+        for s2_tile in s2_tiles.values() {
+            assert_eq!(S2CellId::from_token(&s2_tile.token), s2_tile.cell_id);
+            assert_eq!(s2_tile.vertices.exterior().0.len(), 5);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use geo::{Polygon, Rect};
+use geohashrust::BoundingBox;
+
+use crate::cell_id::CellId;
+use crate::crs::Crs;
+
+/// A hierarchical grid that `Tiler` can count coordinates in and split into adaptive tiles.
+///
+/// Cells are identified by packed `CellId`s while counting and tiling, and by string tokens in
+/// tiling results. Every cell at precision `p` has exactly one parent at each coarser precision,
+/// and the cells at `min_precision` cover the whole extent of the scheme.
+pub trait SpatialScheme {
+    fn max_precision(&self) -> u8;
+
+    /// The coarsest precision emitted as a tile.
+    fn min_precision(&self) -> u8 {
+        1
+    }
+
+    /// The CRS of cells and coordinates. For a projected CRS, `encode` takes y as latitude and x
+    /// as longitude, and `bounds` returns x and y in the longitude and latitude fields.
+    fn crs(&self) -> Crs {
+        Crs::Wgs84
+    }
+
+    /// The area covered by the cells at `min_precision`. Coordinates outside of it are invalid.
+    fn extent(&self) -> BoundingBox {
+        BoundingBox::from_coordinates(-90.0, 90.0, -180.0, 180.0)
+    }
+
+    fn encode(&self, latitude: f64, longitude: f64, precision: u8) -> CellId;
+
+    fn precision(&self, cell: CellId) -> u8;
+
+    /// Returns the ancestor of `cell` at `precision`, which must not be finer than the cell itself.
+    fn parent(&self, cell: CellId, precision: u8) -> CellId;
+
+    fn children(&self, cell: CellId) -> Vec<CellId>;
+
+    /// The cells at `min_precision`.
+    fn root_cells(&self) -> Vec<CellId>;
+
+    fn bounds(&self, cell: CellId) -> BoundingBox;
+
+    fn polygon(&self, cell: CellId) -> Polygon<f64> {
+        let bounds = self.bounds(cell);
+        Rect::new(
+            (bounds.min_lon, bounds.min_lat),
+            (bounds.max_lon, bounds.max_lat),
+        )
+        .to_polygon()
+    }
+
+    /// The string form of `cell` used as tile id, such as a binary hash or a quadkey.
+    fn token(&self, cell: CellId) -> String;
+
+    /// Parses a tile id produced by `token`. Panics if `token` is not a valid cell of the scheme.
+    fn parse_token(&self, token: &str) -> CellId;
+}
+
+/// Shifts the negative longitudes of a ring of (longitude, latitude) pairs that crosses the
+/// antimeridian by 360, so that the ring stays continuous.
+pub(crate) fn unwrap_antimeridian(coordinates: &mut [(f64, f64)]) {
+    let (min_lon, max_lon) = coordinates
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min_lon, max_lon), (lon, _)| {
+            (min_lon.min(*lon), max_lon.max(*lon))
+        });
+    if max_lon - min_lon > 180.0 {
+        for (lon, _) in coordinates.iter_mut() {
+            if *lon < 0.0 {
+                *lon += 360.0;
+            }
+        }
+    }
+}
+
+/// The bounding box of (longitude, latitude) pairs.
+pub(crate) fn ring_bounds(coordinates: impl IntoIterator<Item = (f64, f64)>) -> BoundingBox {
+    coordinates.into_iter().fold(
+        BoundingBox {
+            min_lat: f64::MAX,
+            max_lat: f64::MIN,
+            min_lon: f64::MAX,
+            max_lon: f64::MIN,
+        },
+        |bounds, (lon, lat)| BoundingBox {
+            min_lat: bounds.min_lat.min(lat),
+            max_lat: bounds.max_lat.max(lat),
+            min_lon: bounds.min_lon.min(lon),
+            max_lon: bounds.max_lon.max(lon),
+        },
+    )
+}
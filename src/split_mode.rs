@@ -0,0 +1,24 @@
+/// How `Tiler` splits a tile that is heavier than `max_allowed_features_in_binary_hash`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SplitMode {
+    /// Split into the cells at the next precision. Binary hash levels alternate longitude and
+    /// latitude cuts, so every other precision has tiles of half the aspect ratio.
+    #[default]
+    Bisect,
+    /// Split into the cells two precisions down, cutting both axes of a binary hash at once, so
+    /// that all tiles are squares. The root extent must be square, giving tiles at even
+    /// precisions, or twice as wide as it is high like the globe, giving tiles at odd precisions.
+    /// `binary_hash_precision` must have the same parity. Must be set before adding coordinates
+    /// under `adaptive_precision`.
+    Quadtree,
+}
+
+impl SplitMode {
+    /// The number of precisions between a tile and the tiles it is split into.
+    pub(crate) fn levels_per_split(&self) -> u8 {
+        match self {
+            SplitMode::Bisect => 1,
+            SplitMode::Quadtree => 2,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::binary_hash_scheme::BinaryHashScheme;
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::crs::Crs;
+use crate::spatial_scheme::SpatialScheme;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileAssignment<S: SpatialScheme = BinaryHashScheme> {
+    spatial_scheme: S,
+    /// Tile cells mapped to their tile ids.
+    cells: HashMap<CellId, String>,
+    /// Distinct tile precisions, finest first. Tiles never overlap, so at most one of them matches.
+    precisions: Vec<u8>,
+}
+
+impl TileAssignment {
+    pub fn new(binary_hash_tiles: &HashMap<String, BinaryHashTile>) -> Self {
+        TileAssignment::with_scheme(BinaryHashScheme::default(), binary_hash_tiles)
+    }
+}
+
+impl<S: SpatialScheme> TileAssignment<S> {
+    pub fn with_scheme(spatial_scheme: S, tiles: &HashMap<String, BinaryHashTile>) -> Self {
+        let cells: HashMap<CellId, String> = tiles
+            .keys()
+            .map(|token| (spatial_scheme.parse_token(token), token.clone()))
+            .collect();
+        let precisions: BTreeSet<u8> = cells
+            .keys()
+            .map(|cell| spatial_scheme.precision(*cell))
+            .collect();
+
+        TileAssignment {
+            cells,
+            precisions: precisions.into_iter().rev().collect(),
+            spatial_scheme,
+        }
+    }
+
+    /// The ids of the tiles coordinates are assigned to, in no particular order.
+    pub fn tile_ids(&self) -> impl Iterator<Item = &str> {
+        self.cells.values().map(|tile_id| tile_id.as_str())
+    }
+
+    pub fn tile_for(&self, latitude: f64, longitude: f64) -> Option<&str> {
+        let precision = *self.precisions.first()?;
+        let cell = self.spatial_scheme.encode(latitude, longitude, precision);
+        self.tile_for_cell_id(cell)
+    }
+
+    /// Returns the tile containing `x`/`y` in `crs`, transformed to the CRS of the spatial scheme.
+    pub fn tile_for_projected(&self, crs: &Crs, x: f64, y: f64) -> Option<&str> {
+        let (x, y) = crs.transform(&self.spatial_scheme.crs(), x, y);
+        self.tile_for(y, x)
+    }
+
+    /// Returns the tile containing the cell with tile id `cell`.
+    pub fn tile_for_cell(&self, cell: &str) -> Option<&str> {
+        self.tile_for_cell_id(self.spatial_scheme.parse_token(cell))
+    }
+
+    /// Returns the tile containing `cell`, walking up one parent per distinct tile precision.
+    pub fn tile_for_cell_id(&self, cell: CellId) -> Option<&str> {
+        let cell_precision = self.spatial_scheme.precision(cell);
+        self.precisions
+            .iter()
+            .filter(|precision| **precision <= cell_precision)
+            .find_map(|precision| {
+                self.cells
+                    .get(&self.spatial_scheme.parent(cell, *precision))
+                    .map(|tile_id| tile_id.as_str())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiler::Tiler;
+
+    #[test]
+    fn assigns_coordinates_to_tiles() {
+        let mut tiler = Tiler::new(11, 2).unwrap();
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, 2.0).unwrap();
+        tiler.add_coordinate(-1.0, 2.0).unwrap();
+        tiler.add_coordinate(1.0, -1.0).unwrap();
+
+        let tile_assignment = TileAssignment::new(&tiler.get_tiles().unwrap());
+
+        assert_eq!(tile_assignment.tile_for(1.0, 1.0), Some("11"));
+        assert_eq!(tile_assignment.tile_for(-45.0, 100.0), Some("10"));
+        assert_eq!(tile_assignment.tile_for(80.0, -170.0), Some("0"));
+
+        tiler.remove_coordinate(1.0, -1.0).unwrap();
+        let tile_assignment = TileAssignment::new(&tiler.get_tiles().unwrap());
+        assert_eq!(tile_assignment.tile_for(80.0, -170.0), None);
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::spatial_scheme::SpatialScheme;
+
+#[derive(Debug, PartialEq)]
+pub struct TileChanges {
+    pub binary_hash_tiles: HashMap<String, BinaryHashTile>,
+    /// Previous tiles that were split, mapped to the new tiles they were split into.
+    pub split_binary_hashes: HashMap<String, Vec<String>>,
+    /// New tiles that replace several previous tiles, mapped to the previous tiles they merged.
+    pub merged_binary_hashes: HashMap<String, Vec<String>>,
+    pub unchanged_binary_hashes: Vec<String>,
+    /// New tiles covering an area that had no tile in the previous tiling.
+    pub added_binary_hashes: Vec<String>,
+    /// Previous tiles whose area has no tile in the new tiling.
+    pub removed_binary_hashes: Vec<String>,
+}
+
+impl TileChanges {
+    pub(crate) fn new<S: SpatialScheme>(
+        spatial_scheme: &S,
+        previous_cell_tiles: &HashMap<CellId, BinaryHashTile>,
+        cell_tiles: HashMap<CellId, BinaryHashTile>,
+    ) -> Self {
+        let token = |cell: &CellId| spatial_scheme.token(*cell);
+        let tokens = |cells: &[CellId]| -> Vec<String> {
+            let mut tokens: Vec<String> = cells.iter().map(token).collect();
+            tokens.sort();
+            tokens
+        };
+
+        let mut previous_cells_by_parent: HashMap<CellId, Vec<CellId>> = HashMap::new();
+        for previous_cell in previous_cell_tiles.keys() {
+            for precision in
+                spatial_scheme.min_precision()..spatial_scheme.precision(*previous_cell)
+            {
+                previous_cells_by_parent
+                    .entry(spatial_scheme.parent(*previous_cell, precision))
+                    .or_default()
+                    .push(*previous_cell);
+            }
+        }
+
+        let mut split_cells: HashMap<CellId, Vec<CellId>> = HashMap::new();
+        let mut merged_binary_hashes = HashMap::new();
+        let mut unchanged_cells = Vec::new();
+        let mut added_cells = Vec::new();
+        let mut matched_previous_cells = HashSet::new();
+
+        for cell in cell_tiles.keys() {
+            if previous_cell_tiles.contains_key(cell) {
+                unchanged_cells.push(*cell);
+                matched_previous_cells.insert(*cell);
+            } else if let Some(previous_cells) = previous_cells_by_parent.get(cell) {
+                matched_previous_cells.extend(previous_cells.iter().copied());
+                merged_binary_hashes.insert(token(cell), tokens(previous_cells));
+            } else if let Some(previous_cell) = (spatial_scheme.min_precision()
+                ..spatial_scheme.precision(*cell))
+                .map(|precision| spatial_scheme.parent(*cell, precision))
+                .find(|parent| previous_cell_tiles.contains_key(parent))
+            {
+                split_cells.entry(previous_cell).or_default().push(*cell);
+                matched_previous_cells.insert(previous_cell);
+            } else {
+                added_cells.push(*cell);
+            }
+        }
+
+        let removed_cells: Vec<CellId> = previous_cell_tiles
+            .keys()
+            .filter(|previous_cell| !matched_previous_cells.contains(*previous_cell))
+            .copied()
+            .collect();
+
+        TileChanges {
+            split_binary_hashes: split_cells
+                .iter()
+                .map(|(previous_cell, cells)| (token(previous_cell), tokens(cells)))
+                .collect(),
+            merged_binary_hashes,
+            unchanged_binary_hashes: tokens(&unchanged_cells),
+            added_binary_hashes: tokens(&added_cells),
+            removed_binary_hashes: tokens(&removed_cells),
+            binary_hash_tiles: cell_tiles
+                .into_iter()
+                .map(|(cell, binary_hash_tile)| (token(&cell), binary_hash_tile))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use geo::Polygon;
+use serde_json::{json, Value};
+
+use crate::binary_hash_tile::BinaryHashTile;
+use crate::spatial_scheme::SpatialScheme;
+
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Builds a GeoJSON FeatureCollection with one polygon per tile of `spatial_scheme`, such as a
+/// rectangle for binary hashes or a hexagon for H3 cells.
+///
+/// Each feature carries `binary_hash` (the tile id), `node_count`, `weight`, `depth` (the precision
+/// of the tile) and `density`, the number of nodes per square kilometre of the tile polygon.
+pub fn tiles_to_geojson<S: SpatialScheme>(
+    spatial_scheme: &S,
+    binary_hash_tiles: &HashMap<String, BinaryHashTile>,
+) -> Value {
+    let mut binary_hashes: Vec<&String> = binary_hash_tiles.keys().collect();
+    binary_hashes.sort();
+
+    let features: Vec<Value> = binary_hashes
+        .into_iter()
+        .map(|binary_hash| {
+            let binary_hash_tile = &binary_hash_tiles[binary_hash];
+            let cell = spatial_scheme.parse_token(binary_hash);
+            let polygon = spatial_scheme.polygon(cell);
+            let coordinates: Vec<[f64; 2]> = polygon
+                .exterior()
+                .coords()
+                .map(|coordinate| [coordinate.x, coordinate.y])
+                .collect();
+            json!({
+                "type": "Feature",
+                "bbox": [
+                    binary_hash_tile.min_lon,
+                    binary_hash_tile.min_lat,
+                    binary_hash_tile.max_lon,
+                    binary_hash_tile.max_lat,
+                ],
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [coordinates],
+                },
+                "properties": {
+                    "binary_hash": binary_hash,
+                    "node_count": binary_hash_tile.node_count,
+                    "weight": binary_hash_tile.weight,
+                    "depth": spatial_scheme.precision(cell),
+                    "density": binary_hash_tile.node_count as f64 / area_km2(&polygon),
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+pub fn write_tiles_geojson<S: SpatialScheme, W: Write>(
+    spatial_scheme: &S,
+    binary_hash_tiles: &HashMap<String, BinaryHashTile>,
+    writer: W,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    serde_json::to_writer(
+        &mut writer,
+        &tiles_to_geojson(spatial_scheme, binary_hash_tiles),
+    )?;
+    writer.flush()
+}
+
+pub fn write_tiles_geojson_file<S: SpatialScheme>(
+    spatial_scheme: &S,
+    binary_hash_tiles: &HashMap<String, BinaryHashTile>,
+    path: &Path,
+) -> io::Result<()> {
+    write_tiles_geojson(spatial_scheme, binary_hash_tiles, File::create(path)?)
+}
+
+/// The area of `polygon` on a sphere, with edges following lines of constant slope in
+/// longitude and latitude. This is exact for longitude/latitude rectangles.
+fn area_km2(polygon: &Polygon<f64>) -> f64 {
+    let twice_area: f64 = polygon
+        .exterior()
+        .lines()
+        .map(|line| {
+            (line.end.x - line.start.x).to_radians()
+                * (2.0 + line.start.y.to_radians().sin() + line.end.y.to_radians().sin())
+        })
+        .sum();
+    EARTH_RADIUS_KM * EARTH_RADIUS_KM * twice_area.abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_hash_scheme::BinaryHashScheme;
+    #[cfg(feature = "h3")]
+    use crate::h3_scheme::H3Scheme;
+
+    #[test]
+    fn exports_tiles_as_feature_collection() {
+        let binary_hash_tiles = HashMap::from([(
+            String::from("1"),
+            BinaryHashTile {
+                node_count: 5,
+                weight: 5.0,
+                min_lon: 0.0,
+                min_lat: -90.0,
+                max_lon: 180.0,
+                max_lat: 90.0,
+                min_x: 0.0,
+                min_y: -90.0,
+                max_x: 180.0,
+                max_y: 90.0,
+            },
+        )]);
+
+        let mut output = Vec::new();
+        write_tiles_geojson(
+            &BinaryHashScheme::default(),
+            &binary_hash_tiles,
+            &mut output,
+        )
+        .unwrap();
+        let feature_collection: Value = serde_json::from_slice(&output).unwrap();
+
+        let feature = &feature_collection["features"][0];
+        assert_eq!(feature_collection["type"], "FeatureCollection");
+        assert_eq!(feature["geometry"]["type"], "Polygon");
+        assert_eq!(
+            feature["geometry"]["coordinates"][0],
+            json!([
+                [180.0, -90.0],
+                [180.0, 90.0],
+                [0.0, 90.0],
+                [0.0, -90.0],
+                [180.0, -90.0]
+            ])
+        );
+        assert_eq!(feature["properties"]["binary_hash"], "1");
+        assert_eq!(feature["properties"]["node_count"], 5);
+        assert_eq!(feature["properties"]["depth"], 1);
+
+        // Half of the earth's surface.
+        let density = feature["properties"]["density"].as_f64().unwrap();
+        let half_earth_area_km2 = 2.0 * std::f64::consts::PI * EARTH_RADIUS_KM * EARTH_RADIUS_KM;
+        assert!((density - 5.0 / half_earth_area_km2).abs() < 1e-15);
+
+        #[cfg(feature = "h3")]
+        {
+            let h3_token = H3Scheme.token(H3Scheme.encode(48.2, 16.4, 5));
+            let h3_tiles = HashMap::from([(h3_token, binary_hash_tiles["1"].clone())]);
+            let feature_collection = tiles_to_geojson(&H3Scheme, &h3_tiles);
+            let feature = &feature_collection["features"][0];
+            assert_eq!(feature["properties"]["depth"], 5);
+            // A closed hexagon rather than the bounding rectangle of the cell.
+            let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+            assert_eq!(ring.len(), 7);
+            assert_eq!(ring[0], ring[6]);
+            // Resolution 5 H3 cells average about 252.9 square kilometres.
+            let density = feature["properties"]["density"].as_f64().unwrap();
+            assert!((5.0 / density - 252.9).abs() < 50.0);
+        }
+    }
+}
@@ -1,165 +1,2130 @@
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+#[cfg(feature = "parquet")]
+use std::fs::File;
+#[cfg(feature = "parquet")]
+use std::path::Path;
 
-use geohashrust::{BinaryHash, GeoLocation};
-use polars::prelude::*;
+use geo::{BoundingRect, Coord, Geometry, MapCoords};
+use geohashrust::BoundingBox;
+#[cfg(feature = "h3")]
+use h3o::CellIndex;
+#[cfg(feature = "parquet")]
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+#[cfg(feature = "parquet")]
+use parquet::errors::ParquetError;
 
+use crate::balanced_tiles::BalancedTiles;
+use crate::binary_hash_scheme::BinaryHashScheme;
 use crate::binary_hash_tile::BinaryHashTile;
+use crate::cell_id::CellId;
+use crate::crs::Crs;
+use crate::geometry_assignment::GeometryAssignment;
+#[cfg(feature = "parquet")]
+use crate::geoparquet::{float64_column, read_wkb_column, GeometryColumn};
+#[cfg(feature = "h3")]
+use crate::h3_scheme::{H3Scheme, H3Tile};
+use crate::invalid_coordinate_policy::InvalidCoordinatePolicy;
+use crate::merged_tile::MergedTile;
+use crate::overflow_policy::OverflowPolicy;
+use crate::quadkey_scheme::{QuadkeyScheme, QuadkeyTile, XyzTile};
+use crate::s2_scheme::{S2CellId, S2Scheme, S2Tile};
+use crate::spatial_scheme::SpatialScheme;
+use crate::split_mode::SplitMode;
+use crate::tile_changes::TileChanges;
+use crate::tiler_error::TilerError;
 
-pub struct Tiler {
+/// A cell with the node count and weight counted in it.
+pub(crate) type CountedCell = (CellId, i64, f64);
+
+pub struct Tiler<S: SpatialScheme = BinaryHashScheme> {
+    pub spatial_scheme: S,
     pub binary_hash_precision: u8,
     pub max_allowed_features_in_binary_hash: u64,
-    pub binary_hash_count: HashMap<String, i64>,
+    /// Tiles with less weight are merged with adjacent tiles by `get_merged_tiles`.
+    pub min_features_in_tile: u64,
+    /// Also emit tiles without coordinates for the cells at `min_precision` and the children of
+    /// split cells, so that the tiles partition the whole extent of the scheme.
+    pub include_empty_tiles: bool,
+    pub binary_hash_count: HashMap<CellId, i64>,
+    pub binary_hash_weight: HashMap<CellId, f64>,
+    pub previous_binary_hash_tiles: HashMap<CellId, BinaryHashTile>,
+    pub invalid_coordinate_policy: InvalidCoordinatePolicy,
+    /// Coordinates left out under `InvalidCoordinatePolicy::Skip`.
+    pub skipped_coordinate_count: u64,
+    pub overflow_policy: OverflowPolicy,
+    /// Raw `(latitude, longitude, weight)` coordinates per cell, kept under
+    /// `OverflowPolicy::Refine`.
+    pub retained_coordinates: HashMap<CellId, Vec<(f64, f64, f64)>>,
+    /// Counts coordinates in cells that start at `min_precision` and are split only once they are
+    /// heavier than `max_allowed_features_in_binary_hash`, with `binary_hash_precision` as the
+    /// finest precision. Must be set before adding coordinates.
+    pub adaptive_precision: bool,
+    /// Cells split under `adaptive_precision`.
+    pub refined_cells: HashSet<CellId>,
+    /// The cells at `binary_hash_precision` counted in each cell coarser than
+    /// `binary_hash_precision`, sorted and with their node count and weight, kept under
+    /// `adaptive_precision` to split it later. A cell holding more than
+    /// `max_allowed_features_in_binary_hash + 1` of them is split, so that each cell keeps a
+    /// bounded number of entries however light its coordinates are.
+    pub unrefined_cells: HashMap<CellId, Vec<CountedCell>>,
+    /// The CRS of coordinates given to `add_projected_coordinate` and of geometries given to
+    /// `add_geometry` and `add_parquet`, transformed to the CRS of the spatial scheme. Tiles
+    /// report their x/y bounds in it.
+    pub input_crs: Crs,
+    pub split_mode: SplitMode,
+    /// The cells at `binary_hash_precision` of each geometry counted in more than one cell, so
+    /// that tiles count such a geometry once however many of its cells they contain.
+    pub multi_cell_geometries: Vec<Vec<CellId>>,
 }
 
 impl Tiler {
-    pub fn new(binary_hash_precision: u8, max_allowed_features_in_binary_hash: u64) -> Self {
-        Tiler {
-            binary_hash_precision: binary_hash_precision,
-            max_allowed_features_in_binary_hash: max_allowed_features_in_binary_hash,
+    pub fn new(
+        binary_hash_precision: u8,
+        max_allowed_features_in_binary_hash: u64,
+    ) -> Result<Self, TilerError> {
+        Tiler::with_scheme(
+            BinaryHashScheme::default(),
+            binary_hash_precision,
+            max_allowed_features_in_binary_hash,
+        )
+    }
+
+    /// Bisects `root_extent` instead of the whole globe, so that binary hashes of a city-scale
+    /// dataset do not start with the levels that narrow the globe down to the city.
+    pub fn with_root_extent(
+        root_extent: BoundingBox,
+        binary_hash_precision: u8,
+        max_allowed_features_in_binary_hash: u64,
+    ) -> Result<Self, TilerError> {
+        Tiler::with_scheme(
+            BinaryHashScheme::with_root_extent(root_extent),
+            binary_hash_precision,
+            max_allowed_features_in_binary_hash,
+        )
+    }
+
+    /// Tiles coordinates in `crs` in that planar space, bisecting x and y within its extent.
+    pub fn projected(
+        crs: Crs,
+        binary_hash_precision: u8,
+        max_allowed_features_in_binary_hash: u64,
+    ) -> Result<Self, TilerError> {
+        let mut tiler = Tiler::with_scheme(
+            BinaryHashScheme::projected(crs),
+            binary_hash_precision,
+            max_allowed_features_in_binary_hash,
+        )?;
+        tiler.input_crs = crs;
+        Ok(tiler)
+    }
+}
+
+impl Tiler<QuadkeyScheme> {
+    pub fn quadkey(zoom: u8, max_allowed_features_in_binary_hash: u64) -> Result<Self, TilerError> {
+        Tiler::with_scheme(QuadkeyScheme, zoom, max_allowed_features_in_binary_hash)
+    }
+
+    /// Like `get_tiles`, but keyed by z/x/y and with the quadkey of each tile.
+    pub fn get_xyz_tiles(&self) -> Result<HashMap<XyzTile, QuadkeyTile>, TilerError> {
+        Ok(QuadkeyTile::from_cell_tiles(self.get_cell_tiles()?))
+    }
+}
+
+#[cfg(feature = "h3")]
+impl Tiler<H3Scheme> {
+    pub fn h3(
+        resolution: u8,
+        max_allowed_features_in_binary_hash: u64,
+    ) -> Result<Self, TilerError> {
+        Tiler::with_scheme(H3Scheme, resolution, max_allowed_features_in_binary_hash)
+    }
+
+    /// Like `get_tiles`, but keyed by H3 cell index and with the hexagon boundary of each tile.
+    pub fn get_h3_tiles(&self) -> Result<HashMap<CellIndex, H3Tile>, TilerError> {
+        Ok(H3Tile::from_cell_tiles(self.get_cell_tiles()?))
+    }
+}
+
+impl Tiler<S2Scheme> {
+    pub fn s2(level: u8, max_allowed_features_in_binary_hash: u64) -> Result<Self, TilerError> {
+        Tiler::with_scheme(S2Scheme, level, max_allowed_features_in_binary_hash)
+    }
+
+    /// Like `get_tiles`, but keyed by S2 cell id and with the vertices of each tile.
+    pub fn get_s2_tiles(&self) -> Result<HashMap<S2CellId, S2Tile>, TilerError> {
+        Ok(S2Tile::from_cell_tiles(self.get_cell_tiles()?))
+    }
+}
+
+impl<S: SpatialScheme> Tiler<S> {
+    pub fn with_scheme(
+        spatial_scheme: S,
+        binary_hash_precision: u8,
+        max_allowed_features_in_binary_hash: u64,
+    ) -> Result<Self, TilerError> {
+        let precision_range = spatial_scheme.min_precision()..=spatial_scheme.max_precision();
+        if !precision_range.contains(&binary_hash_precision) {
+            return Err(TilerError::InvalidConfiguration(format!(
+                "binary_hash_precision must be between {} and {}, got {binary_hash_precision}",
+                precision_range.start(),
+                precision_range.end(),
+            )));
+        }
+        spatial_scheme.crs().validate()?;
+        let extent = spatial_scheme.extent();
+        let is_finite = [
+            extent.min_lat,
+            extent.max_lat,
+            extent.min_lon,
+            extent.max_lon,
+        ]
+        .iter()
+        .all(|bound| bound.is_finite());
+        if !is_finite || extent.min_lat >= extent.max_lat || extent.min_lon >= extent.max_lon {
+            return Err(TilerError::InvalidConfiguration(format!(
+                "extent must be finite and non-empty, got longitudes {} to {} and latitudes {} to {}",
+                extent.min_lon, extent.max_lon, extent.min_lat, extent.max_lat,
+            )));
+        }
+
+        Ok(Tiler {
+            spatial_scheme,
+            binary_hash_precision,
+            max_allowed_features_in_binary_hash,
+            min_features_in_tile: 0,
+            include_empty_tiles: false,
             binary_hash_count: HashMap::new(),
+            binary_hash_weight: HashMap::new(),
+            previous_binary_hash_tiles: HashMap::new(),
+            invalid_coordinate_policy: InvalidCoordinatePolicy::default(),
+            skipped_coordinate_count: 0,
+            overflow_policy: OverflowPolicy::default(),
+            retained_coordinates: HashMap::new(),
+            adaptive_precision: false,
+            refined_cells: HashSet::new(),
+            unrefined_cells: HashMap::new(),
+            input_crs: Crs::default(),
+            split_mode: SplitMode::default(),
+            multi_cell_geometries: Vec::new(),
+        })
+    }
+
+    pub fn add_coordinate(&mut self, latitude: f64, longitude: f64) -> Result<(), TilerError> {
+        self.add_weighted_coordinate(latitude, longitude, 1.0)
+    }
+
+    pub fn add_weighted_coordinate(
+        &mut self,
+        latitude: f64,
+        longitude: f64,
+        weight: f64,
+    ) -> Result<(), TilerError> {
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(TilerError::InvalidWeight { weight });
+        }
+        let Some((latitude, longitude)) = self.invalid_coordinate_policy.apply(
+            latitude,
+            longitude,
+            &self.spatial_scheme.extent(),
+        )?
+        else {
+            self.skipped_coordinate_count += 1;
+            return Ok(());
+        };
+
+        let binary_hash = self.encode_coordinate(latitude, longitude);
+        self.increment_binary_hash(binary_hash, 1, weight);
+        if let OverflowPolicy::Refine { .. } = self.overflow_policy {
+            self.retained_coordinates
+                .entry(binary_hash)
+                .or_default()
+                .push((latitude, longitude, weight));
+        }
+        Ok(())
+    }
+
+    pub fn add_projected_coordinate(&mut self, x: f64, y: f64) -> Result<(), TilerError> {
+        self.add_projected_weighted_coordinate(x, y, 1.0)
+    }
+
+    /// Adds `x`/`y` in `input_crs`, transformed to the CRS of the spatial scheme.
+    pub fn add_projected_weighted_coordinate(
+        &mut self,
+        x: f64,
+        y: f64,
+        weight: f64,
+    ) -> Result<(), TilerError> {
+        self.input_crs.validate()?;
+        let (x, y) = self.input_crs.transform(&self.spatial_scheme.crs(), x, y);
+        self.add_weighted_coordinate(y, x, weight)
+    }
+
+    pub fn remove_coordinate(&mut self, latitude: f64, longitude: f64) -> Result<bool, TilerError> {
+        self.remove_weighted_coordinate(latitude, longitude, 1.0)
+    }
+
+    /// Removes a coordinate added before. Returns `false` if there is none in its cell, or if the
+    /// coordinate is invalid and skipped. A `weight` heavier than its cell holds is rejected.
+    pub fn remove_weighted_coordinate(
+        &mut self,
+        latitude: f64,
+        longitude: f64,
+        weight: f64,
+    ) -> Result<bool, TilerError> {
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(TilerError::InvalidWeight { weight });
+        }
+        let Some((latitude, longitude)) = self.invalid_coordinate_policy.apply(
+            latitude,
+            longitude,
+            &self.spatial_scheme.extent(),
+        )?
+        else {
+            return Ok(false);
+        };
+
+        let binary_hash = self.encode_coordinate(latitude, longitude);
+        if !self.decrement_binary_hash(binary_hash, weight)? {
+            return Ok(false);
+        }
+        if let Some(coordinates) = self.retained_coordinates.get_mut(&binary_hash) {
+            if let Some(index) = coordinates
+                .iter()
+                .position(|coordinate| *coordinate == (latitude, longitude, weight))
+            {
+                coordinates.swap_remove(index);
+            }
+            if coordinates.is_empty() {
+                self.retained_coordinates.remove(&binary_hash);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn move_coordinate(
+        &mut self,
+        old_latitude: f64,
+        old_longitude: f64,
+        new_latitude: f64,
+        new_longitude: f64,
+    ) -> Result<bool, TilerError> {
+        if !self.remove_coordinate(old_latitude, old_longitude)? {
+            return Ok(false);
+        }
+        self.add_coordinate(new_latitude, new_longitude)?;
+        Ok(true)
+    }
+
+    /// Counts a geometry in each cell chosen by `geometry_assignment`, with a weight of 1/n in
+    /// each of its n cells so that it adds 1 to the total weight. Point geometries and centroids
+    /// are validated like coordinates, other geometries by the corners of their bounding box.
+    /// The geometry is in `input_crs`, transformed to the CRS of the spatial scheme.
+    pub fn add_geometry(
+        &mut self,
+        geometry: &Geometry<f64>,
+        geometry_assignment: GeometryAssignment,
+    ) -> Result<(), TilerError> {
+        self.input_crs.validate()?;
+        let input_crs = self.input_crs;
+        let scheme_crs = self.spatial_scheme.crs();
+        if input_crs == scheme_crs {
+            return self.add_scheme_geometry(geometry, geometry_assignment);
         }
+        let geometry = geometry.map_coords(|coord| {
+            let (x, y) = input_crs.transform(&scheme_crs, coord.x, coord.y);
+            Coord { x, y }
+        });
+        self.add_scheme_geometry(&geometry, geometry_assignment)
     }
 
-    pub fn add_coordinate(&mut self, latitude: f64, longitude: f64) {
-        let geometry = GeoLocation {
-            latitude: latitude,
-            longitude: longitude,
+    fn add_scheme_geometry(
+        &mut self,
+        geometry: &Geometry<f64>,
+        geometry_assignment: GeometryAssignment,
+    ) -> Result<(), TilerError> {
+        if let Some(point) = geometry_assignment.point(geometry) {
+            return self.add_coordinate(point.y(), point.x());
+        }
+        let Some(bounding_rect) = geometry.bounding_rect() else {
+            return Ok(());
         };
-        let binary_hash = BinaryHash::encode(&geometry, self.binary_hash_precision).to_string();
-        *self.binary_hash_count.entry(binary_hash).or_insert(0) += 1;
+        let extent = self.spatial_scheme.extent();
+        for corner in [bounding_rect.min(), bounding_rect.max()] {
+            if self
+                .invalid_coordinate_policy
+                .apply(corner.y, corner.x, &extent)?
+                .is_none()
+            {
+                self.skipped_coordinate_count += 1;
+                return Ok(());
+            }
+        }
+
+        let binary_hashes =
+            geometry_assignment.cells(&self.spatial_scheme, geometry, self.binary_hash_precision);
+        let weight = 1.0 / binary_hashes.len() as f64;
+        if binary_hashes.len() > 1 {
+            self.multi_cell_geometries
+                .push(binary_hashes.iter().copied().collect());
+        }
+        for binary_hash in binary_hashes {
+            self.increment_binary_hash(binary_hash, 1, weight);
+            if let OverflowPolicy::Refine { .. } = self.overflow_policy {
+                // Refined by the centre of the part of its bounding box within the cell.
+                let bounds = self.spatial_scheme.bounds(binary_hash);
+                let latitude = (bounding_rect.min().y.max(bounds.min_lat)
+                    + bounding_rect.max().y.min(bounds.max_lat))
+                    / 2.0;
+                let longitude = (bounding_rect.min().x.max(bounds.min_lon)
+                    + bounding_rect.max().x.min(bounds.max_lon))
+                    / 2.0;
+                self.retained_coordinates
+                    .entry(binary_hash)
+                    .or_default()
+                    .push((latitude, longitude, weight));
+            }
+        }
+        Ok(())
     }
 
-    pub fn get_tiles(&self) -> Result<HashMap<String, BinaryHashTile>, PolarsError> {
-        let node_count: Vec<i64> = self.binary_hash_count.clone().into_values().collect();
-        let binary_hash: Vec<String> = self.binary_hash_count.clone().into_keys().collect();
+    #[cfg(feature = "parquet")]
+    pub fn add_parquet(
+        &mut self,
+        input_path: &Path,
+        geometry_column: &GeometryColumn,
+        geometry_assignment: GeometryAssignment,
+    ) -> Result<(), TilerError> {
+        let record_batch_reader =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(input_path)?)?.build()?;
 
-        let mut binary_hash_count_df = df!(
-            "node_count" => node_count,
-            "binary_hash" => binary_hash
-        )?;
+        for record_batch in record_batch_reader {
+            let record_batch = record_batch.map_err(ParquetError::from)?;
+            match geometry_column {
+                GeometryColumn::Wkb(geometry_column) => {
+                    for wkb_geometry in read_wkb_column(&record_batch, geometry_column)?
+                        .into_iter()
+                        .flatten()
+                    {
+                        self.add_geometry(&wkb_geometry.geometry, geometry_assignment)?;
+                    }
+                }
+                GeometryColumn::Coordinates {
+                    latitude_column,
+                    longitude_column,
+                } => {
+                    let latitudes = float64_column(&record_batch, latitude_column)?;
+                    let longitudes = float64_column(&record_batch, longitude_column)?;
+                    for (latitude, longitude) in latitudes.iter().zip(longitudes.iter()) {
+                        if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+                            self.add_projected_coordinate(longitude, latitude)?;
+                        }
+                    }
+                }
+            }
+        }
 
-        let mut binary_hash_tiles = HashMap::new();
+        Ok(())
+    }
 
-        for i in 0..self.binary_hash_precision as usize {
-            let sliced_binary_hash: Vec<&str> = binary_hash_count_df
-                .column("binary_hash")?
-                .utf8()?
-                .into_no_null_iter()
-                .map(|binary_hash_value: &str| &binary_hash_value[..i + 1])
-                .collect();
-            let temp_binary_hash_count_df = binary_hash_count_df
-                .with_column(Series::new("sliced_binary_hash", sliced_binary_hash))?
-                .clone();
-
-            let grouped_binary_hash_df = temp_binary_hash_count_df
-                .lazy()
-                .group_by([col("sliced_binary_hash")])
-                .agg([
-                    col("node_count").sum().alias("total_node_count"),
-                    col("binary_hash").reverse().alias("binary_hashes"),
-                ])
-                .collect()?;
-            let binary_hashes_over_max_allowed_features_df = grouped_binary_hash_df
-                .clone()
-                .lazy()
-                .filter(col("total_node_count").gt(lit(self.max_allowed_features_in_binary_hash)))
-                .collect()?
-                .explode(["binary_hashes"])?
-                .rename("binary_hashes", "binary_hash")?
-                .drop_many(&["sliced_binary_hash", "total_node_count"])
-                .left_join(&binary_hash_count_df, ["binary_hash"], ["binary_hash"])?;
-            binary_hash_count_df = binary_hashes_over_max_allowed_features_df;
-
-            let binary_hashes_under_max_allowed_features_df = grouped_binary_hash_df
-                .lazy()
-                .filter(
-                    col("total_node_count").lt(lit(self.max_allowed_features_in_binary_hash + 1)),
+    fn encode_coordinate(&self, latitude: f64, longitude: f64) -> CellId {
+        self.spatial_scheme
+            .encode(latitude, longitude, self.binary_hash_precision)
+    }
+
+    /// Counts `node_count` coordinates with a total of `weight` in `binary_hash`.
+    pub(crate) fn increment_binary_hash(
+        &mut self,
+        binary_hash: CellId,
+        node_count: i64,
+        weight: f64,
+    ) {
+        if !self.adaptive_precision {
+            self.count_in_cell(binary_hash, binary_hash, node_count, weight);
+            return;
+        }
+
+        let cell = self.unrefined_cell(binary_hash);
+        self.count_in_cell(cell, binary_hash, node_count, weight);
+        self.refine_cell(cell);
+    }
+
+    fn count_in_cell(&mut self, cell: CellId, binary_hash: CellId, node_count: i64, weight: f64) {
+        *self.binary_hash_count.entry(cell).or_insert(0) += node_count;
+        *self.binary_hash_weight.entry(cell).or_insert(0.0) += weight;
+        if cell != binary_hash {
+            let counted_cells = self.unrefined_cells.entry(cell).or_default();
+            match counted_cells
+                .binary_search_by_key(&binary_hash, |(counted_cell, _, _)| *counted_cell)
+            {
+                Ok(index) => {
+                    counted_cells[index].1 += node_count;
+                    counted_cells[index].2 += weight;
+                }
+                Err(index) => counted_cells.insert(index, (binary_hash, node_count, weight)),
+            }
+        }
+    }
+
+    /// The cell that counts `binary_hash` under `adaptive_precision`: its coarsest ancestor that
+    /// is not split.
+    fn unrefined_cell(&self, binary_hash: CellId) -> CellId {
+        (self.min_tile_precision()..self.binary_hash_precision)
+            .step_by(self.split_mode.levels_per_split().into())
+            .map(|precision| self.spatial_scheme.parent(binary_hash, precision))
+            .find(|cell| !self.refined_cells.contains(cell))
+            .unwrap_or(binary_hash)
+    }
+
+    /// Splits `cell` into the cells of `split_mode` while it is heavier than
+    /// `max_allowed_features_in_binary_hash` or holds too many unrefined cells, and is coarser
+    /// than `binary_hash_precision`. Splitting a light cell only makes `get_cell_tiles` group its
+    /// children again.
+    fn refine_cell(&mut self, cell: CellId) {
+        let precision = self.spatial_scheme.precision(cell);
+        let is_heavy =
+            self.binary_hash_weight[&cell] > self.max_allowed_features_in_binary_hash as f64;
+        let is_full = self
+            .unrefined_cells
+            .get(&cell)
+            .is_some_and(|counted_cells| {
+                counted_cells.len() as u64
+                    > self.max_allowed_features_in_binary_hash.saturating_add(1)
+            });
+        if precision + self.split_mode.levels_per_split() > self.binary_hash_precision
+            || !(is_heavy || is_full)
+        {
+            return;
+        }
+
+        self.refined_cells.insert(cell);
+        self.binary_hash_count.remove(&cell);
+        self.binary_hash_weight.remove(&cell);
+        let mut children = HashSet::new();
+        for (binary_hash, node_count, weight) in
+            self.unrefined_cells.remove(&cell).unwrap_or_default()
+        {
+            let child = self
+                .spatial_scheme
+                .parent(binary_hash, precision + self.split_mode.levels_per_split());
+            self.count_in_cell(child, binary_hash, node_count, weight);
+            children.insert(child);
+        }
+        for child in children {
+            self.refine_cell(child);
+        }
+    }
+
+    /// Removes one coordinate of `weight` from `binary_hash`. Returns `false` if there is none,
+    /// and rejects a weight that is more than the cell holds.
+    fn decrement_binary_hash(
+        &mut self,
+        binary_hash: CellId,
+        weight: f64,
+    ) -> Result<bool, TilerError> {
+        let cell = if self.adaptive_precision {
+            self.unrefined_cell(binary_hash)
+        } else {
+            binary_hash
+        };
+        let Some(cell_weight) = self.binary_hash_weight.get(&cell) else {
+            return Ok(false);
+        };
+        if !holds_weight(*cell_weight, weight) {
+            return Err(TilerError::InvalidWeight { weight });
+        }
+        if cell != binary_hash {
+            let Some(counted_cells) = self.unrefined_cells.get_mut(&cell) else {
+                return Ok(false);
+            };
+            let Ok(index) = counted_cells
+                .binary_search_by_key(&binary_hash, |(counted_cell, _, _)| *counted_cell)
+            else {
+                return Ok(false);
+            };
+            if !holds_weight(counted_cells[index].2, weight) {
+                return Err(TilerError::InvalidWeight { weight });
+            }
+            counted_cells[index].1 -= 1;
+            counted_cells[index].2 = (counted_cells[index].2 - weight).max(0.0);
+            if counted_cells[index].1 == 0 {
+                counted_cells.remove(index);
+            }
+            if counted_cells.is_empty() {
+                self.unrefined_cells.remove(&cell);
+            }
+        }
+
+        let Some(node_count) = self.binary_hash_count.get_mut(&cell) else {
+            return Ok(false);
+        };
+        *node_count -= 1;
+        if *node_count == 0 {
+            self.binary_hash_count.remove(&cell);
+            self.binary_hash_weight.remove(&cell);
+        } else if let Some(binary_hash_weight) = self.binary_hash_weight.get_mut(&cell) {
+            // Rounding may leave a remainder just below zero.
+            *binary_hash_weight = (*binary_hash_weight - weight).max(0.0);
+        }
+        Ok(true)
+    }
+
+    pub fn get_tiles_incremental(&mut self) -> Result<TileChanges, TilerError> {
+        let cell_tiles = self.get_cell_tiles()?;
+        let tile_changes = TileChanges::new(
+            &self.spatial_scheme,
+            &self.previous_binary_hash_tiles,
+            cell_tiles.clone(),
+        );
+        self.previous_binary_hash_tiles = cell_tiles;
+        Ok(tile_changes)
+    }
+
+    pub fn get_tiles(&self) -> Result<HashMap<String, BinaryHashTile>, TilerError> {
+        Ok(self
+            .get_cell_tiles()?
+            .into_iter()
+            .map(|(cell, binary_hash_tile)| (self.spatial_scheme.token(cell), binary_hash_tile))
+            .collect())
+    }
+
+    /// The tiles that are heavier than `max_allowed_features_in_binary_hash` because they could
+    /// not be split any further, regardless of `overflow_policy`.
+    pub fn get_overflowing_tiles(&self) -> Result<HashMap<String, BinaryHashTile>, TilerError> {
+        Ok(self
+            .split_all_cells()?
+            .into_iter()
+            .filter(|(_, binary_hash_tile)| self.is_overflowing(binary_hash_tile.weight))
+            .map(|(cell, binary_hash_tile)| (self.spatial_scheme.token(cell), binary_hash_tile))
+            .collect())
+    }
+
+    /// The precision of each cell that coordinates are counted in, keyed by tile id. Under
+    /// `adaptive_precision` this is the precision each region was split to, otherwise it is
+    /// `binary_hash_precision` everywhere.
+    pub fn get_effective_precisions(&self) -> HashMap<String, u8> {
+        self.binary_hash_count
+            .keys()
+            .map(|cell| {
+                (
+                    self.spatial_scheme.token(*cell),
+                    self.spatial_scheme.precision(*cell),
                 )
-                .collect()?;
-            let sliced_binary_hash_list: Vec<String> = binary_hashes_under_max_allowed_features_df
-                .column("sliced_binary_hash")?
-                .utf8()?
-                .into_no_null_iter()
-                .map(|geohash| geohash.to_string())
+            })
+            .collect()
+    }
+
+    /// Like `get_tiles`, but keyed by `CellId` instead of tile id.
+    ///
+    /// Cells are sorted once, so that the cells inside any coarser cell form a contiguous run.
+    /// Tiles are then emitted top-down in a single traversal, descending only into cells whose
+    /// weight exceeds `max_allowed_features_in_binary_hash`.
+    pub fn get_cell_tiles(&self) -> Result<HashMap<CellId, BinaryHashTile>, TilerError> {
+        let binary_hash_tiles = self.split_all_cells()?;
+        if self.overflow_policy == OverflowPolicy::Reject {
+            let mut binary_hashes: Vec<String> = binary_hash_tiles
+                .iter()
+                .filter(|(_, binary_hash_tile)| self.is_overflowing(binary_hash_tile.weight))
+                .map(|(cell, _)| self.spatial_scheme.token(*cell))
                 .collect();
-            let node_count_list: Vec<i64> = binary_hashes_under_max_allowed_features_df
-                .column("total_node_count")?
-                .i64()?
-                .into_no_null_iter()
+            if !binary_hashes.is_empty() {
+                binary_hashes.sort();
+                return Err(TilerError::TileOverflow { binary_hashes });
+            }
+        }
+        Ok(binary_hash_tiles)
+    }
+
+    fn is_overflowing(&self, weight: f64) -> bool {
+        weight > self.max_allowed_features_in_binary_hash as f64
+    }
+
+    /// Splits the heaviest tile until there are `tile_count` tiles, instead of splitting every
+    /// tile heavier than `max_allowed_features_in_binary_hash`. Splits that would overshoot
+    /// `tile_count` are skipped, so there are fewer tiles if the cells at `binary_hash_precision`
+    /// cannot be divided any further.
+    pub fn get_tiles_with_count(&self, tile_count: usize) -> Result<BalancedTiles, TilerError> {
+        if tile_count == 0 {
+            return Err(TilerError::InvalidConfiguration(
+                "tile_count must be at least 1".to_string(),
+            ));
+        }
+
+        self.validate_split_mode(self.binary_hash_precision)?;
+        let cells = self.sorted_cells();
+        let mut splittable_tiles: BinaryHeap<SplittableTile> = self
+            .group_cells(&cells, self.min_tile_precision())
+            .into_iter()
+            .map(|(cell, group)| SplittableTile::new(cell, group))
+            .collect();
+        let mut binary_hash_tiles: HashMap<CellId, BinaryHashTile> = self
+            .empty_cells(self.root_tile_cells(), &cells)
+            .into_iter()
+            .map(|cell| (cell, self.cell_tile(cell, &[])))
+            .collect();
+        while splittable_tiles.len() + binary_hash_tiles.len() < tile_count {
+            let Some(splittable_tile) = splittable_tiles.pop() else {
+                break;
+            };
+            let precision = self.spatial_scheme.precision(splittable_tile.cell);
+            let group = splittable_tile.group;
+            if precision < self.binary_hash_precision
+                && self.spatial_scheme.precision(group[0].0) > precision
+            {
+                let children =
+                    self.group_cells(group, precision + self.split_mode.levels_per_split());
+                let empty_children =
+                    self.empty_cells(self.child_tile_cells(splittable_tile.cell), group);
+                if splittable_tiles.len()
+                    + binary_hash_tiles.len()
+                    + children.len()
+                    + empty_children.len()
+                    <= tile_count
+                {
+                    for empty_child in empty_children {
+                        binary_hash_tiles.insert(empty_child, self.cell_tile(empty_child, &[]));
+                    }
+                    splittable_tiles.extend(
+                        children
+                            .into_iter()
+                            .map(|(cell, group)| SplittableTile::new(cell, group)),
+                    );
+                    continue;
+                }
+            }
+            binary_hash_tiles.insert(
+                splittable_tile.cell,
+                self.cell_tile(splittable_tile.cell, group),
+            );
+        }
+        for splittable_tile in splittable_tiles {
+            binary_hash_tiles.insert(
+                splittable_tile.cell,
+                self.cell_tile(splittable_tile.cell, splittable_tile.group),
+            );
+        }
+        self.count_geometries_once(&mut binary_hash_tiles);
+
+        Ok(BalancedTiles::new(
+            binary_hash_tiles
+                .into_iter()
+                .map(|(cell, binary_hash_tile)| (self.spatial_scheme.token(cell), binary_hash_tile))
+                .collect(),
+        ))
+    }
+
+    /// Like `get_tiles`, but merges each tile lighter than `min_features_in_tile` with an adjacent
+    /// tile, as long as the merged tile is not heavier than `max_allowed_features_in_binary_hash`.
+    /// Tiles are adjacent if their cells share an edge or are siblings, and a tile is merged with
+    /// the neighbour it shares the finest ancestor cell with, so sibling tiles are merged first.
+    /// Keyed by the first of the `binary_hashes` of each merged tile.
+    pub fn get_merged_tiles(&self) -> Result<HashMap<String, MergedTile>, TilerError> {
+        let cell_tile_map = self.get_cell_tiles()?;
+        let mut cell_tiles: Vec<(CellId, BinaryHashTile)> = cell_tile_map
+            .iter()
+            .map(|(cell, binary_hash_tile)| (*cell, binary_hash_tile.clone()))
+            .collect();
+        cell_tiles.sort_unstable_by_key(|(cell, _)| *cell);
+        let cells: Vec<CellId> = cell_tiles.iter().map(|(cell, _)| *cell).collect();
+        let adjacent_tiles = self.adjacent_tiles(&cells);
+
+        // Merged tiles are groups of cell tiles, identified by the index of one of their cell
+        // tiles. `groups` is the group of each cell tile, and `group_members` is empty for
+        // indices that do not identify a group.
+        let mut groups: Vec<usize> = (0..cell_tiles.len()).collect();
+        let mut group_members: Vec<Vec<usize>> =
+            (0..cell_tiles.len()).map(|index| vec![index]).collect();
+        let mut group_weights: Vec<f64> = cell_tiles
+            .iter()
+            .map(|(_, binary_hash_tile)| binary_hash_tile.weight)
+            .collect();
+
+        // Weights are never negative, so their bit patterns sort like the weights.
+        let mut light_groups: BinaryHeap<Reverse<(u64, usize)>> = group_weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight < self.min_features_in_tile as f64)
+            .map(|(group, weight)| Reverse((weight.to_bits(), group)))
+            .collect();
+        while let Some(Reverse((weight_bits, group))) = light_groups.pop() {
+            if group_members[group].is_empty() || group_weights[group].to_bits() != weight_bits {
+                continue;
+            }
+
+            let neighbour = group_members[group]
+                .iter()
+                .flat_map(|member| {
+                    adjacent_tiles[*member]
+                        .iter()
+                        .map(move |adjacent_tile| (*member, *adjacent_tile))
+                })
+                .filter(|(_, adjacent_tile)| groups[*adjacent_tile] != group)
+                .filter(|(_, adjacent_tile)| {
+                    group_weights[group] + group_weights[groups[*adjacent_tile]]
+                        <= self.max_allowed_features_in_binary_hash as f64
+                })
+                .filter_map(|(member, adjacent_tile)| {
+                    let neighbour = groups[adjacent_tile];
+                    let ancestor_precision =
+                        self.common_ancestor_precision(cells[member], cells[adjacent_tile])?;
+                    Some((
+                        ancestor_precision,
+                        Reverse(group_weights[neighbour].to_bits()),
+                        Reverse(neighbour),
+                    ))
+                })
+                .max();
+            let Some((_, _, Reverse(neighbour))) = neighbour else {
+                continue;
+            };
+
+            // Relabel the smaller group.
+            let (kept_group, merged_group) =
+                if group_members[group].len() >= group_members[neighbour].len() {
+                    (group, neighbour)
+                } else {
+                    (neighbour, group)
+                };
+            let merged_members = std::mem::take(&mut group_members[merged_group]);
+            for member in &merged_members {
+                groups[*member] = kept_group;
+            }
+            group_members[kept_group].extend(merged_members);
+            group_weights[kept_group] += group_weights[merged_group];
+            if group_weights[kept_group] < self.min_features_in_tile as f64 {
+                light_groups.push(Reverse((group_weights[kept_group].to_bits(), kept_group)));
+            }
+        }
+
+        // Cell tiles count a geometry once each, so a merged tile counts it once too many for
+        // each further cell tile of it that it contains.
+        let tile_precisions = self.tile_precisions(&cell_tile_map);
+        let mut overcounts: HashMap<usize, i64> = HashMap::new();
+        for mut tiles in self.geometry_tiles(|cell| {
+            let tile = self.tile_containing(&cell_tile_map, &tile_precisions, cell)?;
+            cells.binary_search(&tile).ok()
+        }) {
+            tiles.dedup();
+            let mut tile_groups: Vec<usize> = tiles.iter().map(|tile| groups[*tile]).collect();
+            tile_groups.sort_unstable();
+            for pair in tile_groups.windows(2).filter(|pair| pair[0] == pair[1]) {
+                *overcounts.entry(pair[0]).or_insert(0) += 1;
+            }
+        }
+
+        let mut merged_tiles = HashMap::new();
+        for (group, members) in group_members
+            .iter()
+            .enumerate()
+            .filter(|(_, members)| !members.is_empty())
+        {
+            let run: Vec<&(CellId, BinaryHashTile)> =
+                members.iter().map(|member| &cell_tiles[*member]).collect();
+            let mut binary_hashes: Vec<String> = run
+                .iter()
+                .map(|(cell, _)| self.spatial_scheme.token(*cell))
                 .collect();
+            binary_hashes.sort();
+            let merged_tile = MergedTile {
+                binary_hashes,
+                node_count: run.iter().map(|(_, tile)| tile.node_count).sum::<i64>()
+                    - overcounts.get(&group).copied().unwrap_or(0),
+                weight: run.iter().map(|(_, tile)| tile.weight).sum(),
+                min_lon: run
+                    .iter()
+                    .map(|(_, tile)| tile.min_lon)
+                    .fold(f64::INFINITY, f64::min),
+                min_lat: run
+                    .iter()
+                    .map(|(_, tile)| tile.min_lat)
+                    .fold(f64::INFINITY, f64::min),
+                max_lon: run
+                    .iter()
+                    .map(|(_, tile)| tile.max_lon)
+                    .fold(f64::NEG_INFINITY, f64::max),
+                max_lat: run
+                    .iter()
+                    .map(|(_, tile)| tile.max_lat)
+                    .fold(f64::NEG_INFINITY, f64::max),
+                min_x: run
+                    .iter()
+                    .map(|(_, tile)| tile.min_x)
+                    .fold(f64::INFINITY, f64::min),
+                min_y: run
+                    .iter()
+                    .map(|(_, tile)| tile.min_y)
+                    .fold(f64::INFINITY, f64::min),
+                max_x: run
+                    .iter()
+                    .map(|(_, tile)| tile.max_x)
+                    .fold(f64::NEG_INFINITY, f64::max),
+                max_y: run
+                    .iter()
+                    .map(|(_, tile)| tile.max_y)
+                    .fold(f64::NEG_INFINITY, f64::max),
+            };
+            merged_tiles.insert(merged_tile.binary_hashes[0].clone(), merged_tile);
+        }
+        Ok(merged_tiles)
+    }
+
+    /// The precision of the finest cell containing both `cell` and `other_cell`, or `None` if
+    /// they are in different cells at `min_precision`.
+    fn common_ancestor_precision(&self, cell: CellId, other_cell: CellId) -> Option<u8> {
+        let finest_precision = self
+            .spatial_scheme
+            .precision(cell)
+            .min(self.spatial_scheme.precision(other_cell));
+        (self.min_tile_precision()..=finest_precision)
+            .rev()
+            .find(|precision| {
+                self.spatial_scheme.parent(cell, *precision)
+                    == self.spatial_scheme.parent(other_cell, *precision)
+            })
+    }
+
+    /// For each of `cells`, the indices of the cells that share an edge with it or are its
+    /// siblings. Edges are compared on the bounds of the cells in the spatial scheme, so cells that
+    /// are not rectangles are only adjacent to their siblings.
+    fn adjacent_tiles(&self, cells: &[CellId]) -> Vec<Vec<usize>> {
+        let bounds: Vec<BoundingBox> = cells
+            .iter()
+            .map(|cell| self.spatial_scheme.bounds(*cell))
+            .collect();
+        let mut cells_by_min_lon: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut cells_by_min_lat: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut cells_by_parent: HashMap<CellId, Vec<usize>> = HashMap::new();
+        for (index, cell) in cells.iter().enumerate() {
+            cells_by_min_lon
+                .entry(bounds[index].min_lon.to_bits())
+                .or_default()
+                .push(index);
+            cells_by_min_lat
+                .entry(bounds[index].min_lat.to_bits())
+                .or_default()
+                .push(index);
+            let precision = self.spatial_scheme.precision(*cell);
+            if precision > self.min_tile_precision() {
+                cells_by_parent
+                    .entry(self.spatial_scheme.parent(*cell, precision - 1))
+                    .or_default()
+                    .push(index);
+            }
+        }
 
-            for (node_count, sliced_binary_hash) in node_count_list
+        let mut adjacent_tiles: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+        for (index, cell_bounds) in bounds.iter().enumerate() {
+            let eastern_cells = cells_by_min_lon
+                .get(&cell_bounds.max_lon.to_bits())
+                .into_iter()
+                .flatten()
+                .filter(|other| {
+                    cell_bounds.min_lat.max(bounds[**other].min_lat)
+                        < cell_bounds.max_lat.min(bounds[**other].max_lat)
+                });
+            let northern_cells = cells_by_min_lat
+                .get(&cell_bounds.max_lat.to_bits())
                 .into_iter()
-                .zip(sliced_binary_hash_list.into_iter())
+                .flatten()
+                .filter(|other| {
+                    cell_bounds.min_lon.max(bounds[**other].min_lon)
+                        < cell_bounds.max_lon.min(bounds[**other].max_lon)
+                });
+            for other in eastern_cells
+                .chain(northern_cells)
+                .copied()
+                .collect::<Vec<_>>()
             {
-                let bounding_box = BinaryHash::from_string(sliced_binary_hash.as_str()).decode();
-                let binary_hash_tile = BinaryHashTile {
-                    node_count: node_count,
-                    min_lon: bounding_box.min_lon,
-                    min_lat: bounding_box.min_lat,
-                    max_lon: bounding_box.max_lon,
-                    max_lat: bounding_box.max_lat,
-                };
-                binary_hash_tiles.insert(sliced_binary_hash, binary_hash_tile);
+                adjacent_tiles[index].push(other);
+                adjacent_tiles[other].push(index);
+            }
+        }
+        for siblings in cells_by_parent.values() {
+            for sibling in siblings {
+                adjacent_tiles[*sibling].extend(siblings.iter().filter(|other| *other != sibling));
             }
         }
+        for adjacent in &mut adjacent_tiles {
+            adjacent.sort_unstable();
+            adjacent.dedup();
+        }
+        adjacent_tiles
+    }
 
-        let binary_hash_list: Vec<String> = binary_hash_count_df
-            .column("binary_hash")?
-            .utf8()?
-            .into_no_null_iter()
-            .map(|geohash| geohash.to_string())
+    fn sorted_cells(&self) -> Vec<CountedCell> {
+        let mut cells: Vec<CountedCell> = self
+            .binary_hash_count
+            .iter()
+            .map(|(cell, node_count)| (*cell, *node_count, self.binary_hash_weight[cell]))
             .collect();
-        let node_count_list: Vec<i64> = binary_hash_count_df
-            .column("node_count")?
-            .i64()?
-            .into_no_null_iter()
+        cells.sort_unstable_by_key(|(cell, _, _)| *cell);
+        cells
+    }
+
+    fn min_tile_precision(&self) -> u8 {
+        let min_precision = self
+            .spatial_scheme
+            .min_precision()
+            .min(self.binary_hash_precision);
+        match self.split_mode {
+            SplitMode::Bisect => min_precision,
+            SplitMode::Quadtree => match self.square_precision_parity() {
+                Some(parity) if min_precision % 2 != parity => min_precision + 1,
+                _ => min_precision,
+            },
+        }
+    }
+
+    /// The parity of the precisions whose cells are squares: 0 if the extent of the scheme is
+    /// square, 1 if its root cells are, as on the 360×180 globe of binary hashes. Quadtree splits
+    /// need a `binary_hash_precision` of this parity, and `None` if they are not possible.
+    pub fn square_precision_parity(&self) -> Option<u8> {
+        let is_square = |bounds: BoundingBox| {
+            let width = bounds.max_lon - bounds.min_lon;
+            let height = bounds.max_lat - bounds.min_lat;
+            (width - height).abs() <= width.max(height) * 1e-9
+        };
+        if is_square(self.spatial_scheme.extent()) {
+            return Some(0);
+        }
+        self.spatial_scheme
+            .root_cells()
+            .first()
+            .is_some_and(|cell| is_square(self.spatial_scheme.bounds(*cell)))
+            .then_some(1)
+    }
+
+    /// Checks that tiles split down to `max_precision` end up at a precision of `split_mode`.
+    fn validate_split_mode(&self, max_precision: u8) -> Result<(), TilerError> {
+        if self.split_mode != SplitMode::Quadtree {
+            return Ok(());
+        }
+        let Some(parity) = self.square_precision_parity() else {
+            return Err(TilerError::InvalidConfiguration(String::from(
+                "quadtree splits need a square root extent, or one twice as wide as it is high",
+            )));
+        };
+        if max_precision % 2 != parity {
+            return Err(TilerError::InvalidConfiguration(format!(
+                "quadtree splits of this root extent need an {} precision such as {} or {}, got \
+                 {max_precision}",
+                if parity == 0 { "even" } else { "odd" },
+                max_precision.saturating_sub(1),
+                max_precision.saturating_add(1),
+            )));
+        }
+        Ok(())
+    }
+
+    /// The cells at `min_tile_precision` covering the whole extent of the scheme.
+    fn root_tile_cells(&self) -> Vec<CellId> {
+        let mut cells = self.spatial_scheme.root_cells();
+        while cells
+            .first()
+            .is_some_and(|cell| self.spatial_scheme.precision(*cell) < self.min_tile_precision())
+        {
+            cells = cells
+                .into_iter()
+                .flat_map(|cell| self.spatial_scheme.children(cell))
+                .collect();
+        }
+        cells
+    }
+
+    /// The cells that `cell` is split into.
+    fn child_tile_cells(&self, cell: CellId) -> Vec<CellId> {
+        let mut cells = vec![cell];
+        for _ in 0..self.split_mode.levels_per_split() {
+            cells = cells
+                .into_iter()
+                .flat_map(|cell| self.spatial_scheme.children(cell))
+                .collect();
+        }
+        cells
+    }
+
+    /// Groups sorted `cells` by their parent at `precision`.
+    fn group_cells<'a>(
+        &self,
+        cells: &'a [CountedCell],
+        precision: u8,
+    ) -> Vec<(CellId, &'a [CountedCell])> {
+        let mut groups = Vec::new();
+        let mut start = 0;
+        while start < cells.len() {
+            let parent = self.spatial_scheme.parent(cells[start].0, precision);
+            let end = start + cells[start..].partition_point(|(cell, _, _)| parent.contains(*cell));
+            groups.push((parent, &cells[start..end]));
+            start = end;
+        }
+        groups
+    }
+
+    /// The tile of `cell`, counting the coordinates in `group`.
+    pub(crate) fn cell_tile(&self, cell: CellId, group: &[CountedCell]) -> BinaryHashTile {
+        let (node_count, weight) = group.iter().fold(
+            (0, 0.0),
+            |(node_count, weight), (_, cell_node_count, cell_weight)| {
+                (node_count + cell_node_count, weight + cell_weight)
+            },
+        );
+        let bounding_box = self.spatial_scheme.bounds(cell);
+        let scheme_crs = self.spatial_scheme.crs();
+        let wgs84_bounds = scheme_crs.transform_bounds(&bounding_box, &Crs::Wgs84);
+        let source_bounds = scheme_crs.transform_bounds(&bounding_box, &self.input_crs);
+        BinaryHashTile {
+            node_count,
+            weight,
+            min_lon: wgs84_bounds.min_lon,
+            min_lat: wgs84_bounds.min_lat,
+            max_lon: wgs84_bounds.max_lon,
+            max_lat: wgs84_bounds.max_lat,
+            min_x: source_bounds.min_lon,
+            min_y: source_bounds.min_lat,
+            max_x: source_bounds.max_lon,
+            max_y: source_bounds.max_lat,
+        }
+    }
+
+    fn split_all_cells(&self) -> Result<HashMap<CellId, BinaryHashTile>, TilerError> {
+        self.validate_split_mode(self.binary_hash_precision)?;
+        let cells = self.sorted_cells();
+        let mut binary_hash_tiles = HashMap::new();
+        for empty_cell in self.empty_cells(self.root_tile_cells(), &cells) {
+            binary_hash_tiles.insert(empty_cell, self.cell_tile(empty_cell, &[]));
+        }
+        self.split_cells(
+            &cells,
+            self.min_tile_precision(),
+            self.binary_hash_precision,
+            &mut binary_hash_tiles,
+        );
+
+        if let OverflowPolicy::Refine { max_precision } = self.overflow_policy {
+            self.refine_overflowing_tiles(max_precision, &mut binary_hash_tiles)?;
+        }
+        self.count_geometries_once(&mut binary_hash_tiles);
+        Ok(binary_hash_tiles)
+    }
+
+    /// Counts each geometry of `multi_cell_geometries` once in each tile of `binary_hash_tiles`,
+    /// instead of once per cell.
+    fn count_geometries_once(&self, binary_hash_tiles: &mut HashMap<CellId, BinaryHashTile>) {
+        let tile_precisions = self.tile_precisions(binary_hash_tiles);
+        let mut overcounts: HashMap<CellId, i64> = HashMap::new();
+        for tiles in self
+            .geometry_tiles(|cell| self.tile_containing(binary_hash_tiles, &tile_precisions, cell))
+        {
+            for pair in tiles.windows(2).filter(|pair| pair[0] == pair[1]) {
+                *overcounts.entry(pair[0]).or_insert(0) += 1;
+            }
+        }
+        for (cell, overcount) in overcounts {
+            if let Some(binary_hash_tile) = binary_hash_tiles.get_mut(&cell) {
+                binary_hash_tile.node_count -= overcount;
+            }
+        }
+    }
+
+    /// The sorted tiles of the cells of each geometry of `multi_cell_geometries`, with a tile
+    /// repeated for each of its cells, found with `tile_of`.
+    fn geometry_tiles<'a, T: Ord>(
+        &'a self,
+        tile_of: impl Fn(CellId) -> Option<T> + 'a,
+    ) -> impl Iterator<Item = Vec<T>> + 'a {
+        self.multi_cell_geometries.iter().map(move |cells| {
+            let mut tiles: Vec<T> = cells.iter().filter_map(|cell| tile_of(*cell)).collect();
+            tiles.sort_unstable();
+            tiles
+        })
+    }
+
+    /// The distinct precisions of `binary_hash_tiles` that cells at `binary_hash_precision` can
+    /// be in, finest first.
+    fn tile_precisions(&self, binary_hash_tiles: &HashMap<CellId, BinaryHashTile>) -> Vec<u8> {
+        let precisions: BTreeSet<u8> = binary_hash_tiles
+            .keys()
+            .map(|cell| self.spatial_scheme.precision(*cell))
+            .filter(|precision| *precision <= self.binary_hash_precision)
             .collect();
+        precisions.into_iter().rev().collect()
+    }
 
-        for (node_count, binary_hash) in node_count_list
-            .into_iter()
-            .zip(binary_hash_list.into_iter())
+    /// The tile of `binary_hash_tiles` containing `cell` at `binary_hash_precision`.
+    fn tile_containing(
+        &self,
+        binary_hash_tiles: &HashMap<CellId, BinaryHashTile>,
+        tile_precisions: &[u8],
+        cell: CellId,
+    ) -> Option<CellId> {
+        tile_precisions
+            .iter()
+            .map(|precision| self.spatial_scheme.parent(cell, *precision))
+            .find(|tile| binary_hash_tiles.contains_key(tile))
+    }
+
+    /// Re-encodes the retained coordinates of each overflowing tile at `max_precision` and splits
+    /// the tile again down to that precision.
+    fn refine_overflowing_tiles(
+        &self,
+        max_precision: u8,
+        binary_hash_tiles: &mut HashMap<CellId, BinaryHashTile>,
+    ) -> Result<(), TilerError> {
+        if !(self.binary_hash_precision..=self.spatial_scheme.max_precision())
+            .contains(&max_precision)
         {
-            let bounding_box = BinaryHash::from_string(binary_hash.as_str()).decode();
-            let binary_hash_tile = BinaryHashTile {
-                node_count: node_count,
-                min_lon: bounding_box.min_lon,
-                min_lat: bounding_box.min_lat,
-                max_lon: bounding_box.max_lon,
-                max_lat: bounding_box.max_lat,
+            return Err(TilerError::InvalidConfiguration(format!(
+                "refinement max_precision must be between {} and {}, got {max_precision}",
+                self.binary_hash_precision,
+                self.spatial_scheme.max_precision(),
+            )));
+        }
+        self.validate_split_mode(max_precision)?;
+
+        let overflowing_cells: Vec<CellId> = binary_hash_tiles
+            .iter()
+            .filter(|(_, binary_hash_tile)| self.is_overflowing(binary_hash_tile.weight))
+            .map(|(cell, _)| *cell)
+            .collect();
+        for overflowing_cell in overflowing_cells {
+            let Some(coordinates) = self.retained_coordinates.get(&overflowing_cell) else {
+                continue;
             };
-            binary_hash_tiles.insert(binary_hash, binary_hash_tile);
+            if coordinates.len() as i64 != binary_hash_tiles[&overflowing_cell].node_count {
+                continue;
+            }
+
+            let mut refined_cells: HashMap<CellId, (i64, f64)> = HashMap::new();
+            for (latitude, longitude, weight) in coordinates {
+                let refined_cell = self
+                    .spatial_scheme
+                    .encode(*latitude, *longitude, max_precision);
+                let (node_count, cell_weight) = refined_cells.entry(refined_cell).or_default();
+                *node_count += 1;
+                *cell_weight += weight;
+            }
+            let mut cells: Vec<CountedCell> = refined_cells
+                .into_iter()
+                .map(|(cell, (node_count, weight))| (cell, node_count, weight))
+                .collect();
+            cells.sort_unstable_by_key(|(cell, _, _)| *cell);
+
+            binary_hash_tiles.remove(&overflowing_cell);
+            self.split_cell(overflowing_cell, &cells, max_precision, binary_hash_tiles);
         }
+        Ok(())
+    }
 
-        Ok(binary_hash_tiles)
+    /// Groups sorted `cells` by their parent at `precision` and emits each group as a tile, or
+    /// splits it at the next precision if it is too heavy and not yet at `max_precision`.
+    fn split_cells(
+        &self,
+        cells: &[CountedCell],
+        precision: u8,
+        max_precision: u8,
+        binary_hash_tiles: &mut HashMap<CellId, BinaryHashTile>,
+    ) {
+        for (parent, group) in self.group_cells(cells, precision) {
+            let weight: f64 = group.iter().map(|(_, _, weight)| weight).sum();
+            if self.is_overflowing(weight) && precision < max_precision {
+                self.split_cell(parent, group, max_precision, binary_hash_tiles);
+            } else {
+                binary_hash_tiles.insert(parent, self.cell_tile(parent, group));
+            }
+        }
+    }
+
+    /// Splits `cell` into the tiles of `cells` at the next precision of `split_mode`, plus empty
+    /// tiles for its other children under `include_empty_tiles`.
+    fn split_cell(
+        &self,
+        cell: CellId,
+        cells: &[CountedCell],
+        max_precision: u8,
+        binary_hash_tiles: &mut HashMap<CellId, BinaryHashTile>,
+    ) {
+        for empty_child in self.empty_cells(self.child_tile_cells(cell), cells) {
+            binary_hash_tiles.insert(empty_child, self.cell_tile(empty_child, &[]));
+        }
+        self.split_cells(
+            cells,
+            self.spatial_scheme.precision(cell) + self.split_mode.levels_per_split(),
+            max_precision,
+            binary_hash_tiles,
+        );
+    }
+
+    /// The cells of `candidate_cells`, all at one precision, that contain none of `cells`, or
+    /// none unless `include_empty_tiles` is set.
+    fn empty_cells(&self, candidate_cells: Vec<CellId>, cells: &[CountedCell]) -> Vec<CellId> {
+        if !self.include_empty_tiles || candidate_cells.is_empty() {
+            return Vec::new();
+        }
+        let precision = self.spatial_scheme.precision(candidate_cells[0]);
+        let occupied_cells: HashSet<CellId> = cells
+            .iter()
+            .map(|(cell, _, _)| self.spatial_scheme.parent(*cell, precision))
+            .collect();
+        candidate_cells
+            .into_iter()
+            .filter(|candidate_cell| !occupied_cells.contains(candidate_cell))
+            .collect()
     }
 }
 
+/// A tile in `Tiler::get_tiles_with_count`, ordered by weight so that the heaviest is split first.
+struct SplittableTile<'a> {
+    cell: CellId,
+    weight: f64,
+    group: &'a [CountedCell],
+}
+
+impl<'a> SplittableTile<'a> {
+    fn new(cell: CellId, group: &'a [CountedCell]) -> Self {
+        SplittableTile {
+            cell,
+            weight: group.iter().map(|(_, _, weight)| weight).sum(),
+            group,
+        }
+    }
+}
+
+impl PartialEq for SplittableTile<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SplittableTile<'_> {}
+
+impl PartialOrd for SplittableTile<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SplittableTile<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.weight
+            .total_cmp(&other.weight)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+/// Whether `weight` can be removed from a cell holding `cell_weight`, up to rounding.
+fn holds_weight(cell_weight: f64, weight: f64) -> bool {
+    weight <= cell_weight + cell_weight.max(weight) * 1e-9
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::lcg;
+    use crate::tile_assignment::TileAssignment;
 
     #[test]
     fn it_works() {
-        let mut tiler = Tiler::new(11, 10000000);
+        let mut tiler = Tiler::new(11, 10000000).unwrap();
 
-        tiler.add_coordinate(1.0, 1.0);
-        tiler.add_coordinate(1.0, 2.0);
-        tiler.add_coordinate(2.0, 3.0);
-        tiler.add_coordinate(4.0, 1.0);
-        tiler.add_coordinate(1.5, 1.5);
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, 2.0).unwrap();
+        tiler.add_coordinate(2.0, 3.0).unwrap();
+        tiler.add_coordinate(4.0, 1.0).unwrap();
+        tiler.add_coordinate(1.5, 1.5).unwrap();
 
         let binary_hash_tiles = tiler.get_tiles().unwrap();
         let expected_result_tiles = HashMap::from([(
             String::from("1"),
             BinaryHashTile {
                 node_count: 5,
+                weight: 5.0,
                 min_lon: 0.0,
                 min_lat: -90.0,
                 max_lon: 180.0,
                 max_lat: 90.0,
+                min_x: 0.0,
+                min_y: -90.0,
+                max_x: 180.0,
+                max_y: 90.0,
             },
         )]);
         assert_eq!(binary_hash_tiles, expected_result_tiles);
     }
+
+    #[test]
+    fn add_geometry_assigns_cells() {
+        let line_string: Geometry<f64> =
+            geo::LineString::from(vec![(-10.0, 10.0), (10.0, 10.0), (10.0, 60.0)]).into();
+
+        let mut centroid_tiler = Tiler::new(2, 10000000).unwrap();
+        centroid_tiler
+            .add_geometry(&line_string, GeometryAssignment::Centroid)
+            .unwrap();
+        assert_eq!(
+            centroid_tiler.binary_hash_count,
+            HashMap::from([(BinaryHashScheme::default().parse_token("11"), 1)])
+        );
+
+        let mut intersection_tiler = Tiler::new(2, 10000000).unwrap();
+        intersection_tiler
+            .add_geometry(&line_string, GeometryAssignment::Intersection)
+            .unwrap();
+        assert_eq!(
+            intersection_tiler.binary_hash_count,
+            HashMap::from([
+                (BinaryHashScheme::default().parse_token("01"), 1),
+                (BinaryHashScheme::default().parse_token("11"), 1)
+            ])
+        );
+
+        let mut bounding_box_tiler = Tiler::new(4, 10000000).unwrap();
+        bounding_box_tiler
+            .add_geometry(&line_string, GeometryAssignment::BoundingBox)
+            .unwrap();
+        bounding_box_tiler
+            .add_geometry(&line_string, GeometryAssignment::Intersection)
+            .unwrap();
+        assert_eq!(bounding_box_tiler.binary_hash_count.len(), 4);
+        assert_eq!(
+            bounding_box_tiler.binary_hash_count.values().sum::<i64>(),
+            7
+        );
+
+        let binary_hash_tiles = intersection_tiler.get_tiles().unwrap();
+        assert_eq!(binary_hash_tiles.len(), 2);
+        assert_eq!(binary_hash_tiles["0"].node_count, 1);
+        assert_eq!(binary_hash_tiles["1"].node_count, 1);
+        assert_eq!(binary_hash_tiles["0"].weight, 0.5);
+
+        let mut fine_tiler = Tiler::new(6, 1000).unwrap();
+        fine_tiler.overflow_policy = OverflowPolicy::Refine { max_precision: 12 };
+        fine_tiler
+            .add_geometry(&line_string, GeometryAssignment::Intersection)
+            .unwrap();
+        // One line in three cells of tile "1" is one feature of it.
+        let binary_hash_tiles = fine_tiler.get_tiles().unwrap();
+        assert_eq!(binary_hash_tiles["1"].node_count, 1);
+        assert_eq!(binary_hash_tiles["0"].node_count, 1);
+        assert_eq!(fine_tiler.binary_hash_count.values().sum::<i64>(), 4);
+        let total_weight: f64 = binary_hash_tiles
+            .values()
+            .map(|binary_hash_tile| binary_hash_tile.weight)
+            .sum();
+        assert!((total_weight - 1.0).abs() < 1e-12);
+        assert_eq!(fine_tiler.retained_coordinates.len(), 4);
+        for (cell, coordinates) in &fine_tiler.retained_coordinates {
+            let (latitude, longitude, _) = coordinates[0];
+            assert_eq!(fine_tiler.encode_coordinate(latitude, longitude), *cell);
+        }
+
+        // A line across two tiles is one feature of each, and of the tile they are merged into.
+        let line: Geometry<f64> = geo::LineString::from(vec![(80.0, 30.0), (100.0, 30.0)]).into();
+        let mut merging_tiler = Tiler::new(6, 1).unwrap();
+        merging_tiler
+            .add_geometry(&line, GeometryAssignment::Intersection)
+            .unwrap();
+        merging_tiler
+            .add_weighted_coordinate(80.0, 170.0, 0.6)
+            .unwrap();
+        let merging_tiles = merging_tiler.get_tiles().unwrap();
+        assert_eq!(merging_tiles["110"].node_count, 1);
+        assert_eq!(merging_tiles["1110"].node_count, 1);
+        merging_tiler.min_features_in_tile = 1000;
+        let merged_tiles = merging_tiler.get_merged_tiles().unwrap();
+        assert_eq!(merged_tiles["110"].binary_hashes, vec!["110", "1110"]);
+        assert_eq!(merged_tiles["110"].node_count, 1);
+
+        let out_of_range: Geometry<f64> =
+            geo::LineString::from(vec![(170.0, 10.0), (190.0, 10.0)]).into();
+        assert!(matches!(
+            fine_tiler.add_geometry(&out_of_range, GeometryAssignment::Intersection),
+            Err(TilerError::InvalidCoordinate { .. })
+        ));
+        fine_tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Skip;
+        fine_tiler
+            .add_geometry(&out_of_range, GeometryAssignment::BoundingBox)
+            .unwrap();
+        assert_eq!(fine_tiler.skipped_coordinate_count, 1);
+    }
+
+    #[test]
+    fn splits_on_weight() {
+        let mut tiler = Tiler::new(11, 10).unwrap();
+
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, -1.0).unwrap();
+        tiler.add_weighted_coordinate(1.0, 2.0, 8.0).unwrap();
+        tiler.add_weighted_coordinate(-1.0, 2.0, 4.5).unwrap();
+
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+        assert_eq!(binary_hash_tiles.len(), 3);
+        assert_eq!(binary_hash_tiles["0"].node_count, 1);
+        assert_eq!(binary_hash_tiles["0"].weight, 1.0);
+        assert_eq!(binary_hash_tiles["10"].node_count, 1);
+        assert_eq!(binary_hash_tiles["10"].weight, 4.5);
+        assert_eq!(binary_hash_tiles["11"].node_count, 2);
+        assert_eq!(binary_hash_tiles["11"].weight, 9.0);
+    }
+
+    #[test]
+    fn remove_and_move_coordinates_incrementally() {
+        let mut tiler = Tiler::new(11, 2).unwrap();
+
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, 2.0).unwrap();
+        tiler.add_coordinate(-1.0, -1.0).unwrap();
+
+        let tile_changes = tiler.get_tiles_incremental().unwrap();
+        assert_eq!(
+            tile_changes.added_binary_hashes,
+            vec![String::from("0"), String::from("1")]
+        );
+
+        tiler.add_coordinate(-1.0, 2.0).unwrap();
+        let tile_changes = tiler.get_tiles_incremental().unwrap();
+        assert_eq!(
+            tile_changes.unchanged_binary_hashes,
+            vec![String::from("0")]
+        );
+        assert_eq!(
+            tile_changes.split_binary_hashes,
+            HashMap::from([(
+                String::from("1"),
+                vec![String::from("10"), String::from("11")]
+            )])
+        );
+
+        assert!(tiler.move_coordinate(-1.0, 2.0, -1.0, -2.0).unwrap());
+        assert!(tiler.remove_coordinate(-1.0, -1.0).unwrap());
+        assert!(!tiler.remove_coordinate(-50.0, -50.0).unwrap());
+        let tile_changes = tiler.get_tiles_incremental().unwrap();
+        assert_eq!(
+            tile_changes.merged_binary_hashes,
+            HashMap::from([(
+                String::from("1"),
+                vec![String::from("10"), String::from("11")]
+            )])
+        );
+        assert_eq!(
+            tile_changes.unchanged_binary_hashes,
+            vec![String::from("0")]
+        );
+        assert_eq!(tile_changes.binary_hash_tiles["0"].node_count, 1);
+        assert!(tile_changes.removed_binary_hashes.is_empty());
+    }
+
+    #[test]
+    fn validates_configuration_and_coordinates() {
+        assert!(matches!(
+            Tiler::new(0, 10),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            Tiler::new(64, 10),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+        #[cfg(feature = "h3")]
+        {
+            assert!(Tiler::h3(0, 10).is_ok());
+            assert!(Tiler::h3(16, 10).is_err());
+        }
+
+        let mut tiler = Tiler::new(11, 10).unwrap();
+        assert!(matches!(
+            tiler.add_coordinate(f64::NAN, 1.0),
+            Err(TilerError::InvalidCoordinate { .. })
+        ));
+        assert!(tiler.add_coordinate(1.0, 181.0).is_err());
+        assert!(matches!(
+            tiler.add_weighted_coordinate(1.0, 1.0, f64::INFINITY),
+            Err(TilerError::InvalidWeight { .. })
+        ));
+        assert!(tiler.binary_hash_count.is_empty());
+
+        tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Skip;
+        tiler.add_coordinate(f64::NAN, 1.0).unwrap();
+        tiler.add_coordinate(95.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        assert_eq!(tiler.skipped_coordinate_count, 2);
+        assert_eq!(tiler.binary_hash_count.values().sum::<i64>(), 1);
+        assert!(matches!(
+            tiler.remove_weighted_coordinate(1.0, 1.0, f64::NAN),
+            Err(TilerError::InvalidWeight { .. })
+        ));
+        assert!(matches!(
+            tiler.remove_weighted_coordinate(1.0, 1.0, 5.0),
+            Err(TilerError::InvalidWeight { .. })
+        ));
+        assert!(tiler
+            .binary_hash_weight
+            .values()
+            .all(|weight| *weight == 1.0));
+
+        tiler.invalid_coordinate_policy = InvalidCoordinatePolicy::Clamp;
+        tiler.add_coordinate(95.0, 200.0).unwrap();
+        assert!(tiler.add_coordinate(f64::NAN, 1.0).is_err());
+        assert_eq!(tiler.skipped_coordinate_count, 2);
+        assert_eq!(
+            tiler.binary_hash_count[&BinaryHashScheme::default().encode(90.0, 180.0, 11)],
+            1
+        );
+    }
+
+    #[test]
+    fn reports_rejects_and_refines_overflowing_tiles() {
+        let coordinates = [(1.0, 1.0), (1.001, 1.0), (1.0, 1.001), (-40.0, -40.0)];
+
+        let mut tiler = Tiler::new(4, 2).unwrap();
+        for (latitude, longitude) in coordinates {
+            tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+        let overflowing_tiles = tiler.get_overflowing_tiles().unwrap();
+        assert_eq!(overflowing_tiles.len(), 1);
+        assert_eq!(overflowing_tiles.values().next().unwrap().node_count, 3);
+        assert!(tiler.retained_coordinates.is_empty());
+
+        tiler.overflow_policy = OverflowPolicy::Reject;
+        match tiler.get_tiles() {
+            Err(TilerError::TileOverflow { binary_hashes }) => {
+                assert_eq!(
+                    binary_hashes,
+                    overflowing_tiles.keys().cloned().collect::<Vec<_>>()
+                )
+            }
+            _ => panic!("expected a tile overflow"),
+        }
+
+        let mut tiler = Tiler::new(4, 2).unwrap();
+        tiler.overflow_policy = OverflowPolicy::Refine { max_precision: 40 };
+        for (latitude, longitude) in coordinates {
+            tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+        assert!(tiler.get_overflowing_tiles().unwrap().is_empty());
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+        assert_eq!(
+            binary_hash_tiles
+                .values()
+                .map(|binary_hash_tile| binary_hash_tile.node_count)
+                .sum::<i64>(),
+            4
+        );
+        assert!(binary_hash_tiles
+            .keys()
+            .any(|binary_hash| binary_hash.len() > 4));
+
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        tiler.add_coordinate(1.0, 1.0).unwrap();
+        assert_eq!(tiler.get_overflowing_tiles().unwrap().len(), 1);
+        assert!(tiler.remove_coordinate(1.0, 1.0).unwrap());
+        assert!(tiler.get_overflowing_tiles().unwrap().is_empty());
+        assert_eq!(
+            tiler
+                .retained_coordinates
+                .values()
+                .map(Vec::len)
+                .sum::<usize>(),
+            5
+        );
+
+        tiler.overflow_policy = OverflowPolicy::Refine { max_precision: 2 };
+        assert!(matches!(
+            tiler.get_tiles(),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn adaptive_precision_refines_only_heavy_cells() {
+        let mut coordinates = vec![(-40.0, -40.0), (40.0, 100.0)];
+        for index in 0..20 {
+            coordinates.push((1.0 + index as f64 * 0.01, 1.0 + index as f64 * 0.01));
+        }
+
+        let mut fixed_tiler = Tiler::new(40, 5).unwrap();
+        let mut adaptive_tiler = Tiler::new(40, 5).unwrap();
+        adaptive_tiler.adaptive_precision = true;
+        for (latitude, longitude) in &coordinates {
+            fixed_tiler.add_coordinate(*latitude, *longitude).unwrap();
+            adaptive_tiler
+                .add_coordinate(*latitude, *longitude)
+                .unwrap();
+        }
+        assert_eq!(
+            adaptive_tiler.get_tiles().unwrap(),
+            fixed_tiler.get_tiles().unwrap()
+        );
+        assert!(adaptive_tiler.binary_hash_count.len() < fixed_tiler.binary_hash_count.len());
+
+        let effective_precisions = adaptive_tiler.get_effective_precisions();
+        assert_eq!(
+            effective_precisions[&BinaryHashScheme::default()
+                .token(BinaryHashScheme::default().encode(-40.0, -40.0, 1))],
+            1
+        );
+        assert!(effective_precisions
+            .values()
+            .any(|precision| *precision > 1));
+        assert!(fixed_tiler
+            .get_effective_precisions()
+            .values()
+            .all(|precision| *precision == 40));
+
+        for (latitude, longitude) in &coordinates[2..12] {
+            assert!(fixed_tiler
+                .remove_coordinate(*latitude, *longitude)
+                .unwrap());
+            assert!(adaptive_tiler
+                .remove_coordinate(*latitude, *longitude)
+                .unwrap());
+        }
+        assert_eq!(
+            adaptive_tiler.get_tiles().unwrap(),
+            fixed_tiler.get_tiles().unwrap()
+        );
+        assert!(adaptive_tiler
+            .unrefined_cells
+            .values()
+            .flatten()
+            .all(|(_, node_count, _)| *node_count > 0));
+
+        let mut fixed_tiler = Tiler::new(20, 10).unwrap();
+        let mut adaptive_tiler = Tiler::new(20, 10).unwrap();
+        adaptive_tiler.adaptive_precision = true;
+        for tiler in [&mut fixed_tiler, &mut adaptive_tiler] {
+            tiler.add_weighted_coordinate(1.0, 1.0, 9.0).unwrap();
+            tiler.add_weighted_coordinate(1.0, 1.0, 1.0).unwrap();
+            assert!(tiler.remove_weighted_coordinate(1.0, 1.0, 1.0).unwrap());
+            assert!(!tiler.remove_coordinate(1.0, 2.0).unwrap());
+            tiler.add_weighted_coordinate(1.0, 1.0, 5.0).unwrap();
+            tiler.add_coordinate(-1.0, -1.0).unwrap();
+        }
+        assert_eq!(adaptive_tiler.unrefined_cells.values().flatten().count(), 1);
+        assert_eq!(
+            adaptive_tiler.get_tiles().unwrap(),
+            fixed_tiler.get_tiles().unwrap()
+        );
+        assert_eq!(fixed_tiler.get_tiles().unwrap().len(), 2);
+
+        // Light coordinates do not make a cell heavy, but still only a bounded number of
+        // entries is kept per cell.
+        let mut fixed_tiler = Tiler::new(40, 5).unwrap();
+        let mut adaptive_tiler = Tiler::new(40, 5).unwrap();
+        adaptive_tiler.adaptive_precision = true;
+        for index in 0..1000 {
+            let latitude = 1.0 + (index % 40) as f64 * 0.1;
+            let longitude = 1.0 + (index / 40) as f64 * 0.1;
+            for tiler in [&mut fixed_tiler, &mut adaptive_tiler] {
+                tiler
+                    .add_weighted_coordinate(latitude, longitude, 0.001)
+                    .unwrap();
+            }
+        }
+        assert!(adaptive_tiler
+            .unrefined_cells
+            .values()
+            .all(|counted_cells| counted_cells.len() <= 6));
+        let retained_entry_count = adaptive_tiler.binary_hash_count.len()
+            + adaptive_tiler.unrefined_cells.values().flatten().count();
+        assert!(retained_entry_count <= adaptive_tiler.binary_hash_count.len() * 7);
+        assert_eq!(
+            adaptive_tiler.get_tiles().unwrap(),
+            fixed_tiler.get_tiles().unwrap()
+        );
+        assert_eq!(fixed_tiler.get_tiles().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn gets_tiles_with_count() {
+        let mut tiler = Tiler::new(20, 10).unwrap();
+        let mut quadkey_tiler = Tiler::quadkey(10, 10).unwrap();
+        for latitude in 0..32 {
+            for longitude in 0..32 {
+                let latitude = (latitude as f64 + 0.5) * 45.0 / 32.0;
+                let longitude = (longitude as f64 + 0.5) * 90.0 / 32.0;
+                tiler.add_coordinate(latitude, longitude).unwrap();
+                quadkey_tiler.add_coordinate(latitude, longitude).unwrap();
+            }
+        }
+
+        let balanced_tiles = tiler.get_tiles_with_count(8).unwrap();
+        assert_eq!(balanced_tiles.binary_hash_tiles.len(), 8);
+        assert_eq!(
+            balanced_tiles
+                .binary_hash_tiles
+                .values()
+                .map(|binary_hash_tile| binary_hash_tile.node_count)
+                .sum::<i64>(),
+            1024
+        );
+        assert_eq!(balanced_tiles.imbalance_ratio, 1.0);
+        assert_eq!(
+            tiler
+                .get_tiles_with_count(1)
+                .unwrap()
+                .binary_hash_tiles
+                .len(),
+            1
+        );
+
+        let balanced_tiles = quadkey_tiler.get_tiles_with_count(10).unwrap();
+        assert!((8..=10).contains(&balanced_tiles.binary_hash_tiles.len()));
+        assert!(balanced_tiles.imbalance_ratio >= 1.0);
+
+        assert!(matches!(
+            tiler.get_tiles_with_count(0),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn merges_light_tiles_with_siblings() {
+        let mut tiler = Tiler::new(8, 10).unwrap();
+        for index in 0..12 {
+            tiler
+                .add_coordinate(1.0 + index as f64 * 1.5, 1.0 + index as f64 * 1.5)
+                .unwrap();
+        }
+        tiler.add_coordinate(40.0, 40.0).unwrap();
+        tiler.add_coordinate(40.0, 40.0).unwrap();
+        tiler.add_coordinate(-40.0, -40.0).unwrap();
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+
+        let merged_tiles = tiler.get_merged_tiles().unwrap();
+        assert_eq!(merged_tiles.len(), binary_hash_tiles.len());
+        assert!(merged_tiles
+            .iter()
+            .all(|(binary_hash, merged_tile)| merged_tile.binary_hashes == [binary_hash.clone()]));
+
+        tiler.min_features_in_tile = 3;
+        let merged_tiles = tiler.get_merged_tiles().unwrap();
+        let mut binary_hashes: Vec<String> = merged_tiles
+            .values()
+            .flat_map(|merged_tile| merged_tile.binary_hashes.clone())
+            .collect();
+        binary_hashes.sort();
+        let mut expected_binary_hashes: Vec<String> = binary_hash_tiles.keys().cloned().collect();
+        expected_binary_hashes.sort();
+        assert_eq!(binary_hashes, expected_binary_hashes);
+
+        assert_eq!(merged_tiles.len(), 3);
+        let merged_tile = &merged_tiles["11000001"];
+        assert_eq!(merged_tile.binary_hashes, ["11000001", "110001"]);
+        assert_eq!(merged_tile.node_count, 7);
+        assert_eq!(
+            merged_tile.max_lat,
+            binary_hash_tiles["110001"]
+                .max_lat
+                .max(binary_hash_tiles["11000001"].max_lat)
+        );
+        assert_eq!(merged_tiles["0"].node_count, 1);
+    }
+
+    #[test]
+    fn merged_tiles_are_connected() {
+        let mut next = lcg(1);
+        let mut tiler = Tiler::new(12, 20).unwrap();
+        for index in 0..400 {
+            let (latitude, longitude) = if index % 2 == 0 {
+                (48.0 + next() * 2.0, 16.0 + next() * 4.0)
+            } else {
+                (next() * 180.0 - 90.0, next() * 360.0 - 180.0)
+            };
+            tiler.add_coordinate(latitude, longitude).unwrap();
+        }
+        tiler.min_features_in_tile = 8;
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+        let merged_tiles = tiler.get_merged_tiles().unwrap();
+        assert!(merged_tiles.len() < binary_hash_tiles.len());
+
+        let share_edge = |tile: &BinaryHashTile, other_tile: &BinaryHashTile| {
+            let overlaps = |min: f64, max: f64, other_min: f64, other_max: f64| {
+                min.max(other_min) < max.min(other_max)
+            };
+            ((tile.max_lon == other_tile.min_lon || tile.min_lon == other_tile.max_lon)
+                && overlaps(
+                    tile.min_lat,
+                    tile.max_lat,
+                    other_tile.min_lat,
+                    other_tile.max_lat,
+                ))
+                || ((tile.max_lat == other_tile.min_lat || tile.min_lat == other_tile.max_lat)
+                    && overlaps(
+                        tile.min_lon,
+                        tile.max_lon,
+                        other_tile.min_lon,
+                        other_tile.max_lon,
+                    ))
+        };
+        for merged_tile in merged_tiles.values() {
+            assert!(
+                merged_tile.weight <= 20.0 || merged_tile.binary_hashes.len() == 1,
+                "{merged_tile:?}"
+            );
+            let mut connected = vec![merged_tile.binary_hashes[0].clone()];
+            let mut index = 0;
+            while index < connected.len() {
+                let tile = &binary_hash_tiles[&connected[index]];
+                for binary_hash in &merged_tile.binary_hashes {
+                    if !connected.contains(binary_hash)
+                        && share_edge(tile, &binary_hash_tiles[binary_hash])
+                    {
+                        connected.push(binary_hash.clone());
+                    }
+                }
+                index += 1;
+            }
+            assert_eq!(
+                connected.len(),
+                merged_tile.binary_hashes.len(),
+                "{:?}",
+                merged_tile.binary_hashes
+            );
+        }
+    }
+
+    #[test]
+    fn empty_tiles_partition_the_whole_extent() {
+        fn assert_partition<S: SpatialScheme + Clone>(mut tiler: Tiler<S>) {
+            for index in 0..200 {
+                let index = index as f64;
+                tiler
+                    .add_coordinate(48.0 + index * 0.01, 2.0 + index * 0.02)
+                    .unwrap();
+            }
+            let tile_assignment = TileAssignment::with_scheme(
+                tiler.spatial_scheme.clone(),
+                &tiler.get_tiles().unwrap(),
+            );
+            assert!(tile_assignment.tile_for(-60.0, 120.0).is_none());
+
+            tiler.include_empty_tiles = true;
+            let cell_tiles = tiler.get_cell_tiles().unwrap();
+            assert_eq!(
+                cell_tiles
+                    .values()
+                    .map(|binary_hash_tile| binary_hash_tile.node_count)
+                    .sum::<i64>(),
+                200
+            );
+            for cell in cell_tiles.keys() {
+                for precision in tiler.min_tile_precision()..tiler.spatial_scheme.precision(*cell) {
+                    assert!(
+                        !cell_tiles.contains_key(&tiler.spatial_scheme.parent(*cell, precision))
+                    );
+                }
+            }
+            let tiles = tiler.get_tiles().unwrap();
+            let tile_assignment = TileAssignment::with_scheme(tiler.spatial_scheme.clone(), &tiles);
+            for latitude in -17..=17 {
+                for longitude in -35..=35 {
+                    let (latitude, longitude) = (latitude as f64 * 5.0, longitude as f64 * 5.0);
+                    assert!(tile_assignment.tile_for(latitude, longitude).is_some());
+                }
+            }
+
+            let balanced_tiles = tiler.get_tiles_with_count(30).unwrap();
+            assert!(
+                balanced_tiles.binary_hash_tiles.len()
+                    <= 30.max(tiler.spatial_scheme.root_cells().len())
+            );
+            let tile_assignment = TileAssignment::with_scheme(
+                tiler.spatial_scheme.clone(),
+                &balanced_tiles.binary_hash_tiles,
+            );
+            assert!(tile_assignment.tile_for(-60.0, 120.0).is_some());
+        }
+
+        assert_partition(Tiler::new(20, 10).unwrap());
+        assert_partition(Tiler::quadkey(12, 10).unwrap());
+        #[cfg(feature = "h3")]
+        assert_partition(Tiler::h3(7, 10).unwrap());
+        assert_partition(Tiler::s2(12, 10).unwrap());
+    }
+
+    #[test]
+    fn tiles_projected_coordinates_in_planar_and_geographic_space() {
+        let crs = Crs::Utm {
+            zone: 33,
+            north: true,
+        };
+        let coordinates: Vec<(f64, f64)> = [(48.21, 16.37), (47.07, 15.44), (46.62, 14.31)]
+            .iter()
+            .map(|(latitude, longitude)| crs.from_wgs84(*latitude, *longitude))
+            .collect();
+        let mut planar_tiler = Tiler::projected(crs, 20, 1).unwrap();
+        let mut geographic_tiler = Tiler::new(20, 1).unwrap();
+        geographic_tiler.input_crs = crs;
+        for (x, y) in &coordinates {
+            planar_tiler.add_projected_coordinate(*x, *y).unwrap();
+            geographic_tiler.add_projected_coordinate(*x, *y).unwrap();
+        }
+
+        for tiler in [&planar_tiler, &geographic_tiler] {
+            let binary_hash_tiles = tiler.get_tiles().unwrap();
+            assert_eq!(binary_hash_tiles.len(), 3);
+            for (x, y) in &coordinates {
+                let (latitude, longitude) = crs.to_wgs84(*x, *y);
+                assert!(binary_hash_tiles.values().any(|binary_hash_tile| {
+                    (binary_hash_tile.min_x..=binary_hash_tile.max_x).contains(x)
+                        && (binary_hash_tile.min_y..=binary_hash_tile.max_y).contains(y)
+                        && (binary_hash_tile.min_lon..=binary_hash_tile.max_lon)
+                            .contains(&longitude)
+                        && (binary_hash_tile.min_lat..=binary_hash_tile.max_lat).contains(&latitude)
+                }));
+            }
+        }
+        let planar_tile = planar_tiler
+            .get_tiles()
+            .unwrap()
+            .into_values()
+            .next()
+            .unwrap();
+        // Planar tiles bisect the 668 km wide extent of the UTM zone in metres.
+        let bisections = (668000.0 / (planar_tile.max_x - planar_tile.min_x)).log2();
+        assert!((bisections - bisections.round()).abs() < 1e-9);
+
+        // Geometries are read in `input_crs` like coordinates.
+        let projected_line = Geometry::LineString(geo::LineString::from(coordinates.clone()));
+        let line = Geometry::LineString(geo::LineString::from(
+            coordinates
+                .iter()
+                .map(|(x, y)| {
+                    let (latitude, longitude) = crs.to_wgs84(*x, *y);
+                    (longitude, latitude)
+                })
+                .collect::<Vec<_>>(),
+        ));
+        let mut projected_geometry_tiler = Tiler::new(12, 1000).unwrap();
+        projected_geometry_tiler.input_crs = crs;
+        projected_geometry_tiler
+            .add_geometry(&projected_line, GeometryAssignment::Intersection)
+            .unwrap();
+        let mut geometry_tiler = Tiler::new(12, 1000).unwrap();
+        geometry_tiler
+            .add_geometry(&line, GeometryAssignment::Intersection)
+            .unwrap();
+        assert_eq!(
+            projected_geometry_tiler.binary_hash_count,
+            geometry_tiler.binary_hash_count
+        );
+
+        let mut planar_geometry_tiler = Tiler::projected(crs, 20, 1).unwrap();
+        for (x, y) in &coordinates {
+            planar_geometry_tiler
+                .add_geometry(
+                    &Geometry::Point(geo::Point::new(*x, *y)),
+                    GeometryAssignment::Centroid,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            planar_geometry_tiler.binary_hash_count,
+            planar_tiler.binary_hash_count
+        );
+
+        assert!(matches!(
+            Tiler::projected(
+                Crs::Utm {
+                    zone: 61,
+                    north: true
+                },
+                20,
+                1
+            ),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn quadtree_splits_emit_only_square_tiles() {
+        let coordinates: Vec<(f64, f64)> = (0..300)
+            .map(|index| {
+                let index = index as f64;
+                (48.0 + (index * 0.37) % 4.0, 2.0 + (index * 0.73) % 8.0)
+            })
+            .collect();
+        let mut tiler = Tiler::new(15, 5).unwrap();
+        tiler.split_mode = SplitMode::Quadtree;
+        let mut adaptive_tiler = Tiler::new(15, 5).unwrap();
+        adaptive_tiler.split_mode = SplitMode::Quadtree;
+        adaptive_tiler.adaptive_precision = true;
+        for (latitude, longitude) in &coordinates {
+            tiler.add_coordinate(*latitude, *longitude).unwrap();
+            adaptive_tiler
+                .add_coordinate(*latitude, *longitude)
+                .unwrap();
+        }
+
+        let binary_hash_tiles = tiler.get_tiles().unwrap();
+        assert_eq!(adaptive_tiler.get_tiles().unwrap(), binary_hash_tiles);
+        assert!(binary_hash_tiles.len() > 10);
+        for (binary_hash, binary_hash_tile) in &binary_hash_tiles {
+            assert!(!binary_hash.len().is_multiple_of(2));
+            let width = binary_hash_tile.max_lon - binary_hash_tile.min_lon;
+            let height = binary_hash_tile.max_lat - binary_hash_tile.min_lat;
+            assert_eq!(width, height);
+            assert!(binary_hash_tile.weight <= 5.0 || binary_hash.len() == 15);
+            // Each tile was split off a parent two precisions up that was too heavy.
+            let parent = &binary_hash[..binary_hash.len() - 2];
+            let parent_weight: f64 = binary_hash_tiles
+                .iter()
+                .filter(|(other_binary_hash, _)| other_binary_hash.starts_with(parent))
+                .map(|(_, other_binary_hash_tile)| other_binary_hash_tile.weight)
+                .sum();
+            assert!(binary_hash.len() == 1 || parent_weight > 5.0);
+        }
+
+        tiler.include_empty_tiles = true;
+        let area: f64 = tiler
+            .get_tiles()
+            .unwrap()
+            .iter()
+            .map(|(binary_hash, binary_hash_tile)| {
+                assert!(!binary_hash.len().is_multiple_of(2));
+                (binary_hash_tile.max_lon - binary_hash_tile.min_lon)
+                    * (binary_hash_tile.max_lat - binary_hash_tile.min_lat)
+            })
+            .sum();
+        assert_eq!(area, 360.0 * 180.0);
+        assert!(tiler
+            .get_tiles_with_count(10)
+            .unwrap()
+            .binary_hash_tiles
+            .keys()
+            .all(|binary_hash| !binary_hash.len().is_multiple_of(2)));
+
+        let mut even_tiler = Tiler::new(16, 5).unwrap();
+        even_tiler.split_mode = SplitMode::Quadtree;
+        assert!(matches!(
+            even_tiler.get_tiles(),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+
+        // A square root extent is square at even precisions.
+        let root_extent = BoundingBox::from_coordinates(48.0, 52.0, 2.0, 6.0);
+        let mut square_tiler = Tiler::with_root_extent(root_extent, 16, 5).unwrap();
+        square_tiler.split_mode = SplitMode::Quadtree;
+        for (latitude, longitude) in &coordinates {
+            if (2.0..6.0).contains(longitude) {
+                square_tiler.add_coordinate(*latitude, *longitude).unwrap();
+            }
+        }
+        let binary_hash_tiles = square_tiler.get_tiles().unwrap();
+        assert!(binary_hash_tiles.len() > 4);
+        for (binary_hash, binary_hash_tile) in &binary_hash_tiles {
+            assert!(binary_hash.len().is_multiple_of(2));
+            assert_eq!(
+                binary_hash_tile.max_lon - binary_hash_tile.min_lon,
+                binary_hash_tile.max_lat - binary_hash_tile.min_lat
+            );
+        }
+
+        let root_extent = BoundingBox::from_coordinates(48.0, 51.0, 2.0, 6.0);
+        let mut oblong_tiler = Tiler::with_root_extent(root_extent, 16, 5).unwrap();
+        oblong_tiler.split_mode = SplitMode::Quadtree;
+        assert!(matches!(
+            oblong_tiler.get_tiles(),
+            Err(TilerError::InvalidConfiguration(_))
+        ));
+    }
 }
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "parquet")]
+use parquet::errors::ParquetError;
+#[cfg(feature = "polars")]
+use polars::prelude::PolarsError;
+
+#[derive(Debug)]
+pub enum TilerError {
+    /// A latitude or longitude that is NaN, infinite or outside the extent of the spatial scheme.
+    InvalidCoordinate {
+        latitude: f64,
+        longitude: f64,
+    },
+    /// A weight that is NaN, infinite or negative, or a removed weight that is more than its cell
+    /// holds.
+    InvalidWeight {
+        weight: f64,
+    },
+    /// A `Tiler` setting outside what its spatial scheme supports.
+    InvalidConfiguration(String),
+    /// Tiles at `binary_hash_precision` heavier than `max_allowed_features_in_binary_hash`,
+    /// under `OverflowPolicy::Reject`.
+    TileOverflow {
+        binary_hashes: Vec<String>,
+    },
+    Io(io::Error),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetError),
+    #[cfg(feature = "polars")]
+    Polars(PolarsError),
+}
+
+impl fmt::Display for TilerError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilerError::InvalidCoordinate {
+                latitude,
+                longitude,
+            } => write!(
+                formatter,
+                "invalid coordinate: latitude {latitude}, longitude {longitude}"
+            ),
+            TilerError::InvalidWeight { weight } => write!(formatter, "invalid weight {weight}"),
+            TilerError::InvalidConfiguration(message) => {
+                write!(formatter, "invalid configuration: {message}")
+            }
+            TilerError::TileOverflow { binary_hashes } => write!(
+                formatter,
+                "{} tiles exceed the maximum allowed features: {}",
+                binary_hashes.len(),
+                binary_hashes.join(", ")
+            ),
+            TilerError::Io(error) => error.fmt(formatter),
+            #[cfg(feature = "parquet")]
+            TilerError::Parquet(error) => error.fmt(formatter),
+            #[cfg(feature = "polars")]
+            TilerError::Polars(error) => error.fmt(formatter),
+        }
+    }
+}
+
+impl Error for TilerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TilerError::Io(error) => Some(error),
+            #[cfg(feature = "parquet")]
+            TilerError::Parquet(error) => Some(error),
+            #[cfg(feature = "polars")]
+            TilerError::Polars(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TilerError {
+    fn from(error: io::Error) -> Self {
+        TilerError::Io(error)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<ParquetError> for TilerError {
+    fn from(error: ParquetError) -> Self {
+        TilerError::Parquet(error)
+    }
+}
+
+#[cfg(feature = "polars")]
+impl From<PolarsError> for TilerError {
+    fn from(error: PolarsError) -> Self {
+        TilerError::Polars(error)
+    }
+}
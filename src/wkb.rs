@@ -0,0 +1,188 @@
+use geo::{
+    Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon,
+    Point, Polygon,
+};
+
+pub(crate) struct WkbGeometry {
+    pub geometry: Geometry<f64>,
+    /// GeoParquet geometry type name, such as `Polygon` or `Point Z`.
+    pub geometry_type: String,
+}
+
+pub(crate) fn read_wkb(bytes: &[u8]) -> Result<WkbGeometry, String> {
+    let mut wkb_reader = WkbReader { bytes, offset: 0 };
+    wkb_reader.read_geometry()
+}
+
+pub(crate) fn write_wkb_point(longitude: f64, latitude: f64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(21);
+    bytes.push(1);
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&longitude.to_le_bytes());
+    bytes.extend_from_slice(&latitude.to_le_bytes());
+    bytes
+}
+
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl WkbReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + N)
+            .ok_or_else(|| String::from("unexpected end of WKB"))?;
+        self.offset += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32, String> {
+        let bytes = self.take::<4>()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_f64(&mut self, little_endian: bool) -> Result<f64, String> {
+        let bytes = self.take::<8>()?;
+        Ok(if little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_geometry(&mut self) -> Result<WkbGeometry, String> {
+        let little_endian = match self.take::<1>()?[0] {
+            0 => false,
+            1 => true,
+            byte_order => return Err(format!("invalid WKB byte order {byte_order}")),
+        };
+        let raw_geometry_type = self.read_u32(little_endian)?;
+
+        // ISO WKB encodes Z and M as thousands, EWKB as high bit flags.
+        let mut has_z = raw_geometry_type & 0x8000_0000 != 0;
+        let mut has_m = raw_geometry_type & 0x4000_0000 != 0;
+        if raw_geometry_type & 0x2000_0000 != 0 {
+            self.read_u32(little_endian)?;
+        }
+        let iso_geometry_type = raw_geometry_type & 0x0fff_ffff;
+        match iso_geometry_type / 1000 {
+            1 => has_z = true,
+            2 => has_m = true,
+            3 => {
+                has_z = true;
+                has_m = true;
+            }
+            _ => {}
+        }
+        let dimensions = 2 + has_z as usize + has_m as usize;
+
+        let (geometry, geometry_type): (Geometry<f64>, _) = match iso_geometry_type % 1000 {
+            1 => (
+                Point(self.read_coord(little_endian, dimensions)?).into(),
+                "Point",
+            ),
+            2 => (
+                self.read_line_string(little_endian, dimensions)?.into(),
+                "LineString",
+            ),
+            3 => (
+                self.read_polygon(little_endian, dimensions)?.into(),
+                "Polygon",
+            ),
+            4 => {
+                let points = self
+                    .read_children(little_endian)?
+                    .into_iter()
+                    .filter_map(|geometry| Point::try_from(geometry).ok())
+                    .collect::<Vec<_>>();
+                (MultiPoint(points).into(), "MultiPoint")
+            }
+            5 => {
+                let line_strings = self
+                    .read_children(little_endian)?
+                    .into_iter()
+                    .filter_map(|geometry| LineString::try_from(geometry).ok())
+                    .collect::<Vec<_>>();
+                (MultiLineString(line_strings).into(), "MultiLineString")
+            }
+            6 => {
+                let polygons = self
+                    .read_children(little_endian)?
+                    .into_iter()
+                    .filter_map(|geometry| Polygon::try_from(geometry).ok())
+                    .collect::<Vec<_>>();
+                (MultiPolygon(polygons).into(), "MultiPolygon")
+            }
+            7 => (
+                Geometry::GeometryCollection(GeometryCollection(
+                    self.read_children(little_endian)?,
+                )),
+                "GeometryCollection",
+            ),
+            _ => return Err(format!("unsupported WKB geometry type {raw_geometry_type}")),
+        };
+
+        let geometry_type = if has_z {
+            format!("{geometry_type} Z")
+        } else {
+            geometry_type.to_string()
+        };
+        Ok(WkbGeometry {
+            geometry,
+            geometry_type,
+        })
+    }
+
+    fn read_coord(&mut self, little_endian: bool, dimensions: usize) -> Result<Coord<f64>, String> {
+        let x = self.read_f64(little_endian)?;
+        let y = self.read_f64(little_endian)?;
+        for _ in 2..dimensions {
+            self.read_f64(little_endian)?;
+        }
+        Ok(Coord { x, y })
+    }
+
+    fn read_line_string(
+        &mut self,
+        little_endian: bool,
+        dimensions: usize,
+    ) -> Result<LineString<f64>, String> {
+        let coord_count = self.read_u32(little_endian)?;
+        let coords = (0..coord_count)
+            .map(|_| self.read_coord(little_endian, dimensions))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(LineString(coords))
+    }
+
+    fn read_polygon(
+        &mut self,
+        little_endian: bool,
+        dimensions: usize,
+    ) -> Result<Polygon<f64>, String> {
+        let ring_count = self.read_u32(little_endian)?;
+        let mut rings = (0..ring_count)
+            .map(|_| self.read_line_string(little_endian, dimensions))
+            .collect::<Result<Vec<_>, _>>()?;
+        if rings.is_empty() {
+            return Ok(Polygon::new(LineString(Vec::new()), Vec::new()));
+        }
+        let exterior = rings.remove(0);
+        Ok(Polygon::new(exterior, rings))
+    }
+
+    fn read_children(&mut self, little_endian: bool) -> Result<Vec<Geometry<f64>>, String> {
+        let geometry_count = self.read_u32(little_endian)?;
+        (0..geometry_count)
+            .map(|_| {
+                self.read_geometry()
+                    .map(|wkb_geometry| wkb_geometry.geometry)
+            })
+            .collect()
+    }
+}